@@ -195,12 +195,31 @@ fn bench_graph_scaling(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_precompute_recommendations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("precompute_recommendations");
+    group.sample_size(10);
+
+    let graph = setup_graph_with_relationships(10_000, 0.0002);
+
+    group.bench_function("serial", |b| {
+        b.iter(|| graph.precompute_recommendations(10));
+    });
+
+    #[cfg(feature = "rayon")]
+    group.bench_function("parallel", |b| {
+        b.iter(|| graph.precompute_recommendations_parallel(10));
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_graph_construction,
     bench_edge_operations,
     bench_recommendation_algorithms,
     bench_connection_queries,
-    bench_graph_scaling
+    bench_graph_scaling,
+    bench_precompute_recommendations
 );
 criterion_main!(benches);
\ No newline at end of file
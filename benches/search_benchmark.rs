@@ -96,7 +96,7 @@ fn setup_search_engine(product_count: usize, with_relationships: bool) -> Search
                 ];
                 let relation = &relation_types[rng.gen_range(0..relation_types.len())];
 
-                engine.add_product_relation(id1, id2, weight, relation.clone());
+                engine.add_product_relation(id1, id2, weight, relation.clone()).unwrap();
             }
         }
     }
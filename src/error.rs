@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// Error type shared by the crate's fallible operations. Stock reservations
+/// keep their own `StockError`, which carries quantities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MegastoreError {
+    UnknownProduct(u64),
+    DuplicateId(u64),
+    InvalidProduct { id: u64, reason: String },
+    ImportError(String),
+    GraphError(String),
+}
+
+impl fmt::Display for MegastoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MegastoreError::UnknownProduct(id) => write!(f, "product {} is not in the index", id),
+            MegastoreError::DuplicateId(id) => write!(f, "product id {} is already in use", id),
+            MegastoreError::InvalidProduct { id, reason } => write!(f, "invalid product {}: {}", id, reason),
+            MegastoreError::ImportError(message) => write!(f, "import failed: {}", message),
+            MegastoreError::GraphError(message) => write!(f, "graph error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for MegastoreError {}
+
+impl From<serde_json::Error> for MegastoreError {
+    fn from(err: serde_json::Error) -> Self {
+        MegastoreError::ImportError(err.to_string())
+    }
+}
@@ -1,6 +1,22 @@
-use petgraph::graph::{NodeIndex, UnGraph};
+use crate::error::MegastoreError;
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
 use petgraph::visit::EdgeRef;
-use std::collections::{HashMap, HashSet};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Graphs with at most this many products get exact betweenness scores in
+/// `bridge_products`; larger graphs are sampled.
+pub const BRIDGE_EXACT_THRESHOLD: usize = 1_000;
+
+/// Number of source products used to estimate betweenness on large graphs.
+pub const BRIDGE_SAMPLE_SIZE: usize = 256;
+
+/// PageRank stops early once the total rank moved in an iteration drops
+/// below this.
+const PAGERANK_TOLERANCE: f64 = 1e-9;
 
 #[derive(Debug, Clone)]
 pub struct ProductNode {
@@ -12,19 +28,74 @@ pub struct ProductNode {
 pub struct EdgeWeight {
     pub weight: f32,
     pub relation_type: RelationType,
+    /// Seconds since the Unix epoch when the relation was last observed, if
+    /// the caller recorded it through `add_edge_at`.
+    pub updated_at: Option<u64>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RelationType {
     Similar,
     BoughtTogether,
     SameCategory,
     SameBrand,
+    AlsoViewed,
+    /// Store-specific relationship such as "accessory of" or "replacement for".
+    Custom(String),
+}
+
+impl fmt::Display for RelationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelationType::Similar => write!(f, "Similar"),
+            RelationType::BoughtTogether => write!(f, "BoughtTogether"),
+            RelationType::SameCategory => write!(f, "SameCategory"),
+            RelationType::SameBrand => write!(f, "SameBrand"),
+            RelationType::AlsoViewed => write!(f, "AlsoViewed"),
+            RelationType::Custom(label) => write!(f, "{}", label),
+        }
+    }
+}
+
+fn relation_multiplier(relation_type: &RelationType) -> f32 {
+    match relation_type {
+        RelationType::BoughtTogether => 1.5,
+        RelationType::Similar => 1.3,
+        RelationType::AlsoViewed => 1.2,
+        RelationType::SameBrand => 1.1,
+        RelationType::SameCategory => 1.0,
+        RelationType::Custom(_) => 1.0,
+    }
+}
+
+fn second_hop_multiplier(relation_type: &RelationType) -> f32 {
+    match relation_type {
+        RelationType::BoughtTogether => 0.75,
+        RelationType::Similar => 0.65,
+        RelationType::AlsoViewed => 0.6,
+        RelationType::SameBrand => 0.55,
+        RelationType::SameCategory => 0.5,
+        RelationType::Custom(_) => 0.5,
+    }
+}
+
+/// How `import_relations` resolves a relation whose product pair is already
+/// connected. The default is `KeepStrongest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RelationMergePolicy {
+    /// Keep whichever weight (and its relation type) is higher.
+    #[default]
+    KeepStrongest,
+    /// Overwrite with the most recently imported weight and type.
+    KeepLatest,
+    /// Add the weights together, keeping the existing relation type.
+    Sum,
 }
 
 pub struct RecommendationGraph {
     graph: UnGraph<ProductNode, EdgeWeight>,
     product_to_node: HashMap<u64, NodeIndex>,
+    last_tick: Option<u64>,
 }
 
 impl RecommendationGraph {
@@ -32,9 +103,50 @@ impl RecommendationGraph {
         RecommendationGraph {
             graph: UnGraph::new_undirected(),
             product_to_node: HashMap::new(),
+            last_tick: None,
         }
     }
 
+    /// Builds a graph from item-item co-occurrence counts, linking each pair
+    /// with `relation` and a weight scaled into [0, 1] by the largest count.
+    /// A pair listed in both directions gets a single edge with the larger
+    /// count; self-pairs and negative or non-finite counts are skipped.
+    /// Products missing from `categories` are filed under "Other".
+    pub fn from_cooccurrence(
+        entries: &[(u64, u64, f32)],
+        relation: RelationType,
+        categories: &HashMap<u64, String>,
+    ) -> Self {
+        let mut graph = RecommendationGraph::new();
+        let category_of = |id: u64| categories.get(&id).cloned().unwrap_or_else(|| "Other".to_string());
+
+        let mut ids: Vec<u64> = categories.keys().copied().collect();
+        ids.extend(entries.iter().flat_map(|&(a, b, _)| [a, b]));
+        ids.sort_unstable();
+        ids.dedup();
+        for id in ids {
+            graph.add_product(id, category_of(id));
+        }
+
+        let valid: Vec<(u64, u64, f32)> = entries
+            .iter()
+            .copied()
+            .filter(|&(a, b, count)| a != b && count.is_finite() && count >= 0.0)
+            .collect();
+        let max = valid.iter().map(|&(_, _, count)| count).fold(0.0, f32::max);
+        if max > 0.0 {
+            let relations: Vec<(u64, u64, f32, RelationType)> = valid
+                .into_iter()
+                .map(|(a, b, count)| (a, b, count / max, relation.clone()))
+                .collect();
+            graph
+                .import_relations(&relations, RelationMergePolicy::KeepStrongest)
+                .expect("co-occurrence pairs are validated above");
+        }
+
+        graph
+    }
+
     pub fn add_product(&mut self, product_id: u64, category: String) -> NodeIndex {
         if let Some(&node_index) = self.product_to_node.get(&product_id) {
             return node_index;
@@ -62,18 +174,70 @@ impl RecommendationGraph {
         self.graph.edge_count()
     }
 
+    pub fn recommendable_count(&self) -> usize {
+        self.graph
+            .node_indices()
+            .filter(|&node| self.graph.neighbors(node).any(|other| other != node))
+            .count()
+    }
+
+    pub fn coverage(&self) -> f64 {
+        let total = self.graph.node_count();
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.recommendable_count() as f64 / total as f64
+    }
+
     pub fn get_product_node(&self, product_id: u64) -> Option<&ProductNode> {
         self.product_to_node
             .get(&product_id)
             .and_then(|&idx| self.graph.node_weight(idx))
     }
 
+    /// Updates the category stored on a product's node. Returns false if the
+    /// product isn't in the graph.
+    pub fn set_category(&mut self, product_id: u64, category: String) -> bool {
+        match self.product_to_node.get(&product_id) {
+            Some(&idx) => {
+                self.graph[idx].category = category;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn add_edge(
         &mut self,
         product_id_1: u64,
         product_id_2: u64,
         weight: f32,
         relation_type: RelationType,
+    ) -> bool {
+        self.insert_edge(product_id_1, product_id_2, weight, relation_type, None)
+    }
+
+    /// `add_edge` stamped with the time the relation was observed, which
+    /// `get_recommendations_recency_weighted` uses to favor fresh relations.
+    pub fn add_edge_at(
+        &mut self,
+        product_id_1: u64,
+        product_id_2: u64,
+        weight: f32,
+        relation_type: RelationType,
+        updated_at: u64,
+    ) -> bool {
+        self.insert_edge(product_id_1, product_id_2, weight, relation_type, Some(updated_at))
+    }
+
+    fn insert_edge(
+        &mut self,
+        product_id_1: u64,
+        product_id_2: u64,
+        weight: f32,
+        relation_type: RelationType,
+        updated_at: Option<u64>,
     ) -> bool {
         if let (Some(&node1), Some(&node2)) = (
             self.product_to_node.get(&product_id_1),
@@ -82,6 +246,7 @@ impl RecommendationGraph {
             let edge_weight = EdgeWeight {
                 weight,
                 relation_type,
+                updated_at,
             };
             self.graph.add_edge(node1, node2, edge_weight);
             true
@@ -90,6 +255,185 @@ impl RecommendationGraph {
         }
     }
 
+    /// Adds every relation, merging pairs that are already connected per
+    /// `policy`. The batch is checked first and nothing is applied if any
+    /// relation names an unknown product, links a product to itself, or has
+    /// a negative or non-finite weight. Returns the number of relations applied.
+    pub fn import_relations(
+        &mut self,
+        relations: &[(u64, u64, f32, RelationType)],
+        policy: RelationMergePolicy,
+    ) -> Result<usize, MegastoreError> {
+        for &(product_id_1, product_id_2, weight, _) in relations {
+            for id in [product_id_1, product_id_2] {
+                if !self.product_to_node.contains_key(&id) {
+                    return Err(MegastoreError::UnknownProduct(id));
+                }
+            }
+            if product_id_1 == product_id_2 {
+                return Err(MegastoreError::GraphError(format!("product {} cannot be related to itself", product_id_1)));
+            }
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(MegastoreError::GraphError(format!("relation weight {} must be a non-negative number", weight)));
+            }
+        }
+
+        let mut applied = 0;
+
+        for (product_id_1, product_id_2, weight, relation_type) in relations {
+            let node1 = self.product_to_node[product_id_1];
+            let node2 = self.product_to_node[product_id_2];
+
+            match self.graph.find_edge(node1, node2) {
+                Some(edge) => {
+                    let existing = &mut self.graph[edge];
+                    match policy {
+                        RelationMergePolicy::KeepStrongest => {
+                            if *weight > existing.weight {
+                                existing.weight = *weight;
+                                existing.relation_type = relation_type.clone();
+                            }
+                        }
+                        RelationMergePolicy::KeepLatest => {
+                            existing.weight = *weight;
+                            existing.relation_type = relation_type.clone();
+                        }
+                        RelationMergePolicy::Sum => {
+                            existing.weight += *weight;
+                        }
+                    }
+                }
+                None => {
+                    self.graph.add_edge(node1, node2, EdgeWeight {
+                        weight: *weight,
+                        relation_type: relation_type.clone(),
+                        updated_at: None,
+                    });
+                }
+            }
+
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    /// Reclassifies every edge between the two products, keeping weights.
+    /// Returns `false` if the products are not connected.
+    pub fn set_relation_type(&mut self, product_id_1: u64, product_id_2: u64, new_type: RelationType) -> bool {
+        let (node1, node2) = match (
+            self.product_to_node.get(&product_id_1),
+            self.product_to_node.get(&product_id_2),
+        ) {
+            (Some(&node1), Some(&node2)) => (node1, node2),
+            _ => return false,
+        };
+
+        let edges: Vec<_> = self.graph.edges_connecting(node1, node2).map(|edge| edge.id()).collect();
+        for &edge in &edges {
+            self.graph[edge].relation_type = new_type.clone();
+        }
+
+        !edges.is_empty()
+    }
+
+    pub fn remove_edges_of_type(&mut self, relation_type: RelationType) -> usize {
+        let edges = self
+            .graph
+            .edge_references()
+            .filter(|edge| edge.weight().relation_type == relation_type)
+            .map(|edge| edge.id())
+            .collect();
+
+        self.remove_edges(edges)
+    }
+
+    /// Halves every edge weight once per `half_life_secs` of `elapsed_secs`.
+    pub fn decay_edges(&mut self, elapsed_secs: u64, half_life_secs: u64) {
+        if half_life_secs == 0 || elapsed_secs == 0 {
+            return;
+        }
+
+        let factor = 0.5f64.powf(elapsed_secs as f64 / half_life_secs as f64) as f32;
+        for edge in self.graph.edge_weights_mut() {
+            edge.weight *= factor;
+        }
+    }
+
+    /// Decays edges by the wall-clock time elapsed since the previous tick.
+    /// The first tick only starts the clock.
+    pub fn tick(&mut self, half_life_secs: u64) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        self.tick_at(now, half_life_secs);
+    }
+
+    /// `tick` with an explicit clock reading in seconds, for callers with
+    /// their own scheduler or time source. Readings that go backwards are
+    /// treated as no time having passed.
+    pub fn tick_at(&mut self, now_secs: u64, half_life_secs: u64) {
+        if let Some(last) = self.last_tick {
+            self.decay_edges(now_secs.saturating_sub(last), half_life_secs);
+        }
+        self.last_tick = Some(self.last_tick.map_or(now_secs, |last| last.max(now_secs)));
+    }
+
+    pub fn prune_weak_edges(&mut self, min_weight: f32) -> usize {
+        let edges = self
+            .graph
+            .edge_references()
+            .filter(|edge| edge.weight().weight < min_weight)
+            .map(|edge| edge.id())
+            .collect();
+
+        self.remove_edges(edges)
+    }
+
+    /// An edge survives only if it is among the `k` strongest of both of its
+    /// endpoints, which guarantees no node keeps more than `k` edges.
+    pub fn prune_to_top_k_per_node(&mut self, k: usize) -> usize {
+        let mut keep: HashMap<EdgeIndex, usize> = HashMap::new();
+
+        for node in self.graph.node_indices() {
+            let mut edges: Vec<_> = self.graph.edges(node).collect();
+            edges.sort_by(|a, b| {
+                b.weight().weight
+                    .partial_cmp(&a.weight().weight)
+                    .unwrap()
+                    .then_with(|| a.id().index().cmp(&b.id().index()))
+            });
+
+            for edge in edges.into_iter().take(k) {
+                *keep.entry(edge.id()).or_insert(0) += 1;
+            }
+        }
+
+        let edges = self
+            .graph
+            .edge_references()
+            .filter(|edge| {
+                let endpoints = if edge.source() == edge.target() { 1 } else { 2 };
+                keep.get(&edge.id()).copied().unwrap_or(0) < endpoints
+            })
+            .map(|edge| edge.id())
+            .collect();
+
+        self.remove_edges(edges)
+    }
+
+    fn remove_edges(&mut self, mut edges: Vec<EdgeIndex>) -> usize {
+        // Removing an edge moves the last edge into its slot, so remove from
+        // the highest index down to keep the collected ids valid.
+        edges.sort_unstable_by_key(|edge| std::cmp::Reverse(edge.index()));
+        for &edge in &edges {
+            self.graph.remove_edge(edge);
+        }
+
+        edges.len()
+    }
+
     pub fn connect_similar_products(&mut self, product_id_1: u64, product_id_2: u64, similarity: f32) {
         self.add_edge(product_id_1, product_id_2, similarity, RelationType::Similar);
     }
@@ -98,6 +442,10 @@ impl RecommendationGraph {
         self.add_edge(product_id_1, product_id_2, frequency, RelationType::BoughtTogether);
     }
 
+    pub fn connect_also_viewed(&mut self, product_id_1: u64, product_id_2: u64, weight: f32) {
+        self.add_edge(product_id_1, product_id_2, weight, RelationType::AlsoViewed);
+    }
+
     pub fn connect_same_category(&mut self, product_id_1: u64, product_id_2: u64) {
         self.add_edge(product_id_1, product_id_2, 0.5, RelationType::SameCategory);
     }
@@ -128,6 +476,63 @@ impl RecommendationGraph {
         }
     }
 
+    pub fn relation_counts(&self, product_id: u64) -> HashMap<RelationType, usize> {
+        let mut counts = HashMap::new();
+        for (_, _, relation_type) in self.get_connections(product_id) {
+            *counts.entry(relation_type).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    pub fn get_top_connections(&self, product_id: u64, limit: usize) -> Vec<(u64, f32, RelationType)> {
+        let mut connections = self.get_connections(product_id);
+        connections.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        connections.truncate(limit);
+        connections
+    }
+
+    /// Borrowing view of every edge, each visited once, for streaming over
+    /// large graphs without materializing them.
+    pub fn edges_iter(&self) -> impl Iterator<Item = (u64, u64, f32, &RelationType)> + '_ {
+        self.graph.edge_references().map(|edge| {
+            let edge_weight = edge.weight();
+            (
+                self.graph[edge.source()].product_id,
+                self.graph[edge.target()].product_id,
+                edge_weight.weight,
+                &edge_weight.relation_type,
+            )
+        })
+    }
+
+    pub fn all_edges(&self) -> Vec<(u64, u64, f32, RelationType)> {
+        self.edges_iter()
+            .map(|(source, target, weight, relation_type)| (source, target, weight, relation_type.clone()))
+            .collect()
+    }
+
+    pub fn to_adjacency_json(&self) -> String {
+        let mut adjacency = serde_json::Map::new();
+
+        for node in self.graph.node_weights() {
+            let neighbors: Vec<serde_json::Value> = self
+                .get_connections(node.product_id)
+                .into_iter()
+                .map(|(id, weight, relation_type)| {
+                    serde_json::json!({
+                        "id": id,
+                        "weight": weight,
+                        "type": relation_type.to_string(),
+                    })
+                })
+                .collect();
+
+            adjacency.insert(node.product_id.to_string(), serde_json::Value::Array(neighbors));
+        }
+
+        serde_json::Value::Object(adjacency).to_string()
+    }
+
     pub fn has_edge(&self, product_id_1: u64, product_id_2: u64) -> bool {
         if let (Some(&node1), Some(&node2)) = (
             self.product_to_node.get(&product_id_1),
@@ -139,19 +544,202 @@ impl RecommendationGraph {
         }
     }
 
+    pub fn hop_distance(&self, product_id_1: u64, product_id_2: u64) -> Option<usize> {
+        let start = *self.product_to_node.get(&product_id_1)?;
+        let target = *self.product_to_node.get(&product_id_2)?;
+
+        if start == target {
+            return Some(0);
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back((start, 0));
+
+        while let Some((node, distance)) = queue.pop_front() {
+            for neighbor in self.graph.neighbors(node) {
+                if neighbor == target {
+                    return Some(distance + 1);
+                }
+                if visited.insert(neighbor) {
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn products_within_hops(&self, product_id: u64, max_hops: usize) -> HashMap<u64, usize> {
+        let mut distances = HashMap::new();
+        let start = match self.product_to_node.get(&product_id) {
+            Some(&node) => node,
+            None => return distances,
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back((start, 0));
+
+        while let Some((node, distance)) = queue.pop_front() {
+            if distance == max_hops {
+                continue;
+            }
+
+            for neighbor in self.graph.neighbors(node) {
+                if visited.insert(neighbor) {
+                    if let Some(neighbor_node) = self.graph.node_weight(neighbor) {
+                        distances.insert(neighbor_node.product_id, distance + 1);
+                    }
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Breadth-first walk from `start`, calling `visit` with the product id,
+    /// edge weight and relation type for every edge leading to a product not
+    /// yet accepted. Returning `false` prunes that branch; a pruned product
+    /// can still be reached later through a different edge. Products deeper
+    /// than `max_depth` hops are never offered.
+    pub fn walk_neighbors<F>(&self, start: u64, max_depth: usize, mut visit: F)
+    where
+        F: FnMut(u64, f32, &RelationType) -> bool,
+    {
+        let start = match self.product_to_node.get(&start) {
+            Some(&node) => node,
+            None => return,
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(start);
+        queue.push_back((start, 0));
+
+        while let Some((node, depth)) = queue.pop_front() {
+            if depth == max_depth {
+                continue;
+            }
+
+            for edge in self.graph.edges(node) {
+                let neighbor = if edge.source() == node { edge.target() } else { edge.source() };
+                if visited.contains(&neighbor) {
+                    continue;
+                }
+
+                let product_id = self.graph[neighbor].product_id;
+                let weight = edge.weight();
+                if visit(product_id, weight.weight, &weight.relation_type) {
+                    visited.insert(neighbor);
+                    queue.push_back((neighbor, depth + 1));
+                }
+            }
+        }
+    }
+
     pub fn get_recommendations(&self, product_id: u64, limit: usize) -> Vec<(u64, f32)> {
         let connections = self.get_connections(product_id);
 
         let mut recommendations: Vec<(u64, f32)> = connections
             .into_iter()
-            .map(|(id, weight, relation_type)| {
-                let type_multiplier = match relation_type {
-                    RelationType::BoughtTogether => 1.5,
-                    RelationType::Similar => 1.3,
-                    RelationType::SameBrand => 1.1,
-                    RelationType::SameCategory => 1.0,
+            .map(|(id, weight, relation_type)| (id, weight * relation_multiplier(&relation_type)))
+            .collect();
+
+        recommendations.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        recommendations.truncate(limit);
+        recommendations
+    }
+
+    /// `get_recommendations` with each edge's contribution multiplied by
+    /// `0.5^(age / half_life_secs)`, where age is `now_secs - updated_at`.
+    /// Edges without a timestamp, or stamped in the future, keep their full
+    /// weight.
+    pub fn get_recommendations_recency_weighted(
+        &self,
+        product_id: u64,
+        now_secs: u64,
+        limit: usize,
+        half_life_secs: u64,
+    ) -> Vec<(u64, f32)> {
+        let node = match self.product_to_node.get(&product_id) {
+            Some(&node) => node,
+            None => return Vec::new(),
+        };
+
+        let mut recommendations: Vec<(u64, f32)> = self
+            .graph
+            .edges(node)
+            .map(|edge| {
+                let edge_weight = edge.weight();
+                let recency = match edge_weight.updated_at {
+                    Some(updated_at) if half_life_secs > 0 => {
+                        let age = now_secs.saturating_sub(updated_at);
+                        0.5f64.powf(age as f64 / half_life_secs as f64) as f32
+                    }
+                    _ => 1.0,
                 };
-                (id, weight * type_multiplier)
+                let score = edge_weight.weight * relation_multiplier(&edge_weight.relation_type) * recency;
+                (self.graph[edge.target()].product_id, score)
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        recommendations.truncate(limit);
+        recommendations
+    }
+
+    pub fn precompute_recommendations(&self, limit: usize) -> HashMap<u64, Vec<(u64, f32)>> {
+        self.product_to_node
+            .keys()
+            .map(|&product_id| (product_id, self.get_recommendations(product_id, limit)))
+            .collect()
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn precompute_recommendations_parallel(&self, limit: usize) -> HashMap<u64, Vec<(u64, f32)>> {
+        self.product_to_node
+            .par_iter()
+            .map(|(&product_id, _)| (product_id, self.get_recommendations(product_id, limit)))
+            .collect()
+    }
+
+    pub fn get_recommendations_multi(&self, product_ids: &[u64], limit: usize) -> Vec<(u64, f32)> {
+        let seeds: HashSet<u64> = product_ids.iter().copied().collect();
+        let mut scores: HashMap<u64, f32> = HashMap::new();
+
+        for &seed in &seeds {
+            for (rec_id, score) in self.get_recommendations(seed, usize::MAX) {
+                if !seeds.contains(&rec_id) {
+                    *scores.entry(rec_id).or_insert(0.0) += score;
+                }
+            }
+        }
+
+        let mut recommendations: Vec<(u64, f32)> = scores.into_iter().collect();
+        recommendations.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        recommendations.truncate(limit);
+        recommendations
+    }
+
+    pub fn get_recommendations_normalized_per_type(&self, product_id: u64, limit: usize) -> Vec<(u64, f32)> {
+        let connections = self.get_connections(product_id);
+
+        let mut max_weights: HashMap<RelationType, f32> = HashMap::new();
+        for (_, weight, relation_type) in &connections {
+            let max_weight = max_weights.entry(relation_type.clone()).or_insert(0.0);
+            *max_weight = max_weight.max(*weight);
+        }
+
+        let mut recommendations: Vec<(u64, f32)> = connections
+            .into_iter()
+            .map(|(id, weight, relation_type)| {
+                let max_weight = max_weights.get(&relation_type).copied().unwrap_or(0.0);
+                let normalized = if max_weight > 0.0 { weight / max_weight } else { 0.0 };
+                (id, normalized * relation_multiplier(&relation_type))
             })
             .collect();
 
@@ -167,28 +755,14 @@ impl RecommendationGraph {
 
         let direct_connections = self.get_connections(product_id);
         for (connected_id, weight, relation_type) in direct_connections {
-            let type_multiplier = match relation_type {
-                RelationType::BoughtTogether => 1.5,
-                RelationType::Similar => 1.3,
-                RelationType::SameBrand => 1.1,
-                RelationType::SameCategory => 1.0,
-            };
-
-            let score = weight * type_multiplier;
+            let score = weight * relation_multiplier(&relation_type);
             scores.insert(connected_id, score);
             visited.insert(connected_id);
 
             let second_level = self.get_connections(connected_id);
             for (second_id, second_weight, second_relation) in second_level {
                 if !visited.contains(&second_id) {
-                    let second_multiplier = match second_relation {
-                        RelationType::BoughtTogether => 0.75,
-                        RelationType::Similar => 0.65,
-                        RelationType::SameBrand => 0.55,
-                        RelationType::SameCategory => 0.5,
-                    };
-
-                    let second_score = score * 0.5 * second_weight * second_multiplier;
+                    let second_score = score * 0.5 * second_weight * second_hop_multiplier(&second_relation);
                     *scores.entry(second_id).or_insert(0.0) += second_score;
                 }
             }
@@ -200,6 +774,42 @@ impl RecommendationGraph {
         recommendations
     }
 
+    /// Same ranking as `get_recommendations_depth_2`, with the path of product
+    /// ids that explains each entry: `[seed, target]` for direct neighbors and
+    /// `[seed, intermediate, target]` through the intermediate contributing the
+    /// most to a second-degree score.
+    pub fn recommendation_paths(&self, product_id: u64, limit: usize) -> Vec<(u64, f32, Vec<u64>)> {
+        let direct = self.get_connections(product_id);
+        let direct_ids: HashSet<u64> = direct.iter().map(|(id, _, _)| *id).collect();
+
+        let mut best_via: HashMap<u64, (u64, f32)> = HashMap::new();
+        for (connected_id, weight, relation_type) in &direct {
+            let score = weight * relation_multiplier(relation_type);
+            for (second_id, second_weight, second_relation) in self.get_connections(*connected_id) {
+                if second_id == product_id || direct_ids.contains(&second_id) {
+                    continue;
+                }
+
+                let contribution = score * 0.5 * second_weight * second_hop_multiplier(&second_relation);
+                let best = best_via.entry(second_id).or_insert((*connected_id, contribution));
+                if contribution > best.1 {
+                    *best = (*connected_id, contribution);
+                }
+            }
+        }
+
+        self.get_recommendations_depth_2(product_id, limit)
+            .into_iter()
+            .map(|(id, score)| {
+                let path = match best_via.get(&id) {
+                    Some(&(via, _)) if !direct_ids.contains(&id) => vec![product_id, via, id],
+                    _ => vec![product_id, id],
+                };
+                (id, score, path)
+            })
+            .collect()
+    }
+
     pub fn get_similar_products(&self, product_id: u64) -> Vec<u64> {
         self.get_connections(product_id)
             .into_iter()
@@ -215,4 +825,172 @@ impl RecommendationGraph {
             .map(|(id, _, _)| id)
             .collect()
     }
-}
\ No newline at end of file
+
+    pub fn get_also_viewed(&self, product_id: u64) -> Vec<u64> {
+        self.get_connections(product_id)
+            .into_iter()
+            .filter(|(_, _, relation_type)| *relation_type == RelationType::AlsoViewed)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    pub fn get_frequently_bought_together_ranked(
+        &self,
+        product_id: u64,
+        min_frequency: f32,
+        limit: usize,
+    ) -> Vec<(u64, f32)> {
+        let mut bought_together: Vec<(u64, f32)> = self
+            .get_connections(product_id)
+            .into_iter()
+            .filter(|(_, weight, relation_type)| {
+                *relation_type == RelationType::BoughtTogether && *weight >= min_frequency
+            })
+            .map(|(id, weight, _)| (id, weight))
+            .collect();
+
+        bought_together.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        bought_together.truncate(limit);
+        bought_together
+    }
+
+    /// Weighted PageRank over the whole graph. Each product passes its rank
+    /// to its neighbors in proportion to edge weight; products without edges
+    /// spread theirs evenly. Ranks sum to 1. Iterates until the ranks settle
+    /// or `max_iterations` is reached.
+    pub fn pagerank(&self, damping: f64, max_iterations: usize) -> HashMap<u64, f64> {
+        let n = self.graph.node_count();
+        if n == 0 {
+            return HashMap::new();
+        }
+
+        let out_weight: Vec<f64> = self
+            .graph
+            .node_indices()
+            .map(|node| self.graph.edges(node).map(|edge| edge.weight().weight as f64).sum())
+            .collect();
+
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..max_iterations {
+            let dangling: f64 = (0..n).filter(|&i| out_weight[i] <= 0.0).map(|i| ranks[i]).sum();
+            let base = (1.0 - damping) / n as f64 + damping * dangling / n as f64;
+            let mut next = vec![base; n];
+
+            for edge in self.graph.edge_references() {
+                let (a, b) = (edge.source().index(), edge.target().index());
+                let weight = edge.weight().weight as f64;
+                if out_weight[a] > 0.0 {
+                    next[b] += damping * ranks[a] * weight / out_weight[a];
+                }
+                if a != b && out_weight[b] > 0.0 {
+                    next[a] += damping * ranks[b] * weight / out_weight[b];
+                }
+            }
+
+            let delta: f64 = ranks.iter().zip(&next).map(|(old, new)| (old - new).abs()).sum();
+            ranks = next;
+            if delta < PAGERANK_TOLERANCE {
+                break;
+            }
+        }
+
+        self.graph
+            .node_indices()
+            .map(|node| (self.graph[node].product_id, ranks[node.index()]))
+            .collect()
+    }
+
+    /// Ranks products by (approximate) betweenness centrality, surfacing the
+    /// ones that connect otherwise separate clusters.
+    ///
+    /// Graphs with up to `BRIDGE_EXACT_THRESHOLD` products run exact Brandes
+    /// over every source. Beyond that, Brandes runs from `BRIDGE_SAMPLE_SIZE`
+    /// evenly spaced sources and the scores are scaled by `n / samples`, so
+    /// results stay comparable but are estimates. Products with a score of zero
+    /// are not returned.
+    pub fn bridge_products(&self, limit: usize) -> Vec<(u64, f32)> {
+        let nodes: Vec<NodeIndex> = self.graph.node_indices().collect();
+        let n = nodes.len();
+        if n < 3 || limit == 0 {
+            return Vec::new();
+        }
+
+        let sources: Vec<usize> = if n <= BRIDGE_EXACT_THRESHOLD {
+            (0..n).collect()
+        } else {
+            let stride = n / BRIDGE_SAMPLE_SIZE;
+            (0..BRIDGE_SAMPLE_SIZE).map(|i| i * stride).collect()
+        };
+
+        let neighbors: Vec<Vec<usize>> = nodes
+            .iter()
+            .map(|&node| {
+                let mut adjacent: Vec<usize> = self
+                    .graph
+                    .neighbors(node)
+                    .filter(|&other| other != node)
+                    .map(|other| other.index())
+                    .collect();
+                adjacent.sort_unstable();
+                adjacent.dedup();
+                adjacent
+            })
+            .collect();
+
+        let mut centrality = vec![0.0f64; n];
+
+        for &source in &sources {
+            let mut stack = Vec::with_capacity(n);
+            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+            let mut path_counts = vec![0.0f64; n];
+            let mut distances = vec![usize::MAX; n];
+            let mut queue = VecDeque::new();
+
+            path_counts[source] = 1.0;
+            distances[source] = 0;
+            queue.push_back(source);
+
+            while let Some(v) = queue.pop_front() {
+                stack.push(v);
+                for &w in &neighbors[v] {
+                    if distances[w] == usize::MAX {
+                        distances[w] = distances[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distances[w] == distances[v] + 1 {
+                        path_counts[w] += path_counts[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.0f64; n];
+            while let Some(w) = stack.pop() {
+                for &v in &predecessors[w] {
+                    dependency[v] += path_counts[v] / path_counts[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+
+        // Each undirected path is counted from both endpoints.
+        let scale = n as f64 / sources.len() as f64 / 2.0;
+
+        let mut bridges: Vec<(u64, f32)> = centrality
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, score)| score > 0.0)
+            .filter_map(|(i, score)| {
+                self.graph
+                    .node_weight(nodes[i])
+                    .map(|node| (node.product_id, (score * scale) as f32))
+            })
+            .collect();
+
+        bridges.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        bridges.truncate(limit);
+        bridges
+    }
+}
@@ -1,6 +1,196 @@
-use crate::models::{Product, Category};
+use crate::error::MegastoreError;
+use crate::models::{normalize_term, Product, Category, StockError};
 use indexmap::IndexMap;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+mod fnv;
+pub(crate) use fnv::Fnv1a;
+
+#[cfg(feature = "tag-sketch")]
+pub mod sketch;
+#[cfg(feature = "tag-sketch")]
+pub use sketch::CountMinSketch;
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrderedF64(pub f64);
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+pub const DEFAULT_MIN_TERM_LENGTH: usize = 2;
+
+/// Default tag sketch error bound: estimates exceed the true count by at most
+/// 0.1% of all tag assignments, 99% of the time.
+#[cfg(feature = "tag-sketch")]
+pub const DEFAULT_SKETCH_EPSILON: f64 = 0.001;
+#[cfg(feature = "tag-sketch")]
+pub const DEFAULT_SKETCH_DELTA: f64 = 0.01;
+
+fn hash_product(product: &Product) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write_u64(product.id);
+    hasher.write_str(&product.name);
+    hasher.write_str(&product.description);
+    hasher.write_str(&product.brand);
+    match product.category {
+        Category::Other(ref name) => {
+            hasher.write_u8(1);
+            hasher.write_str(name);
+        }
+        ref category => {
+            hasher.write_u8(0);
+            hasher.write_str(&category.to_string());
+        }
+    }
+    hasher.write_f64(product.price);
+    hasher.write_u64(product.tags.len() as u64);
+    for tag in &product.tags {
+        hasher.write_str(tag);
+    }
+    hasher.write_u64(u64::from(product.rating.to_bits()));
+    hasher.write_u64(u64::from(product.stock));
+    hasher.write_u64(u64::from(product.review_count));
+    hasher.write_u8(u8::from(product.active));
+    match product.sale_price {
+        Some(price) => {
+            hasher.write_u8(1);
+            hasher.write_f64(price);
+        }
+        None => hasher.write_u8(0),
+    }
+    hasher.write_u64(product.price_tiers.len() as u64);
+    for &(threshold, price) in &product.price_tiers {
+        hasher.write_u64(u64::from(threshold));
+        hasher.write_f64(price);
+    }
+    hasher.write_u64(product.variants.len() as u64);
+    for variant in &product.variants {
+        hasher.write_u64(variant.id);
+        let mut attributes: Vec<_> = variant.attributes.iter().collect();
+        attributes.sort();
+        hasher.write_u64(attributes.len() as u64);
+        for (key, value) in attributes {
+            hasher.write_str(key);
+            hasher.write_str(value);
+        }
+        hasher.write_f64(variant.price_delta);
+        hasher.write_u64(u64::from(variant.stock));
+    }
+    hasher.write_u64(product.restricted_regions.len() as u64);
+    for region in &product.restricted_regions {
+        hasher.write_str(region);
+    }
+    hasher.finish()
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Brand values treated as "no real brand" by `incomplete_products`.
+pub const PLACEHOLDER_BRANDS: &[&str] = &["", "generic", "unknown", "n/a", "none", "-"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompletenessCriteria {
+    pub require_description: bool,
+    pub require_tags: bool,
+    pub require_rating: bool,
+    pub require_brand: bool,
+}
+
+impl CompletenessCriteria {
+    pub fn all() -> Self {
+        CompletenessCriteria {
+            require_description: true,
+            require_tags: true,
+            require_rating: true,
+            require_brand: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        CompletenessCriteria::default()
+    }
+
+    pub fn with_description(mut self) -> Self {
+        self.require_description = true;
+        self
+    }
+
+    pub fn with_tags(mut self) -> Self {
+        self.require_tags = true;
+        self
+    }
+
+    pub fn with_rating(mut self) -> Self {
+        self.require_rating = true;
+        self
+    }
+
+    pub fn with_brand(mut self) -> Self {
+        self.require_brand = true;
+        self
+    }
+
+    fn is_incomplete(&self, product: &Product) -> bool {
+        (self.require_description && product.description.trim().is_empty())
+            || (self.require_tags && product.tags.is_empty())
+            || (self.require_rating && product.rating <= 0.0)
+            || (self.require_brand && PLACEHOLDER_BRANDS.contains(&normalize_term(&product.brand).as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryAggregate {
+    pub count: usize,
+    pub avg_price: f64,
+    pub avg_rating: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// A secondary index bucket references an id that has no product.
+    DanglingId { index: &'static str, key: String, id: u64 },
+    /// A bucket references a product whose fields no longer map to that key.
+    StaleEntry { index: &'static str, key: String, id: u64 },
+    /// A product's field is missing from the bucket it should be in.
+    MissingEntry { index: &'static str, key: String, id: u64 },
+}
 
 #[derive(Debug)]
 pub struct ProductIndex {
@@ -9,6 +199,11 @@ pub struct ProductIndex {
     brand_index: HashMap<String, HashSet<u64>>,
     category_index: HashMap<Category, HashSet<u64>>,
     tag_index: HashMap<String, HashSet<u64>>,
+    /// Region -> products restricted there.
+    region_index: HashMap<String, HashSet<u64>>,
+    #[cfg(feature = "tag-sketch")]
+    tag_sketch: CountMinSketch,
+    min_term_length: usize,
 }
 
 impl ProductIndex {
@@ -19,23 +214,65 @@ impl ProductIndex {
             brand_index: HashMap::new(),
             category_index: HashMap::new(),
             tag_index: HashMap::new(),
+            region_index: HashMap::new(),
+            #[cfg(feature = "tag-sketch")]
+            tag_sketch: CountMinSketch::with_error(DEFAULT_SKETCH_EPSILON, DEFAULT_SKETCH_DELTA),
+            min_term_length: DEFAULT_MIN_TERM_LENGTH,
         }
     }
 
+    /// Name tokens and brands shorter than `length` characters are neither
+    /// indexed nor searched. Raising this cuts noise from tiny tokens, but
+    /// also makes short brand names (e.g. "LG" with a minimum of 3)
+    /// unreachable through `search_by_brand`. Set it before adding products.
+    pub fn with_min_term_length(mut self, length: usize) -> Self {
+        self.min_term_length = length;
+        self
+    }
+
+    /// Replaces the tag frequency sketch with one sized for `epsilon` and
+    /// `delta` (see `CountMinSketch::with_error`). Set it before adding
+    /// products; counts already recorded are discarded.
+    #[cfg(feature = "tag-sketch")]
+    pub fn with_tag_sketch_error(mut self, epsilon: f64, delta: f64) -> Self {
+        self.tag_sketch = CountMinSketch::with_error(epsilon, delta);
+        self
+    }
+
+    /// Approximate number of products carrying `tag`, from a count-min
+    /// sketch of fixed size. `tag_usage` is exact but keeps an id set per
+    /// tag, growing with the catalog; the sketch stays at `width x depth`
+    /// counters (about 108 KB by default) at the cost of overestimating by up
+    /// to `epsilon` times the total number of tag assignments.
+    #[cfg(feature = "tag-sketch")]
+    pub fn approx_tag_frequency(&self, tag: &str) -> u64 {
+        self.tag_sketch.estimate(&normalize_term(tag))
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.split_whitespace()
+            .filter(|word| word.chars().count() >= self.min_term_length)
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+
     pub fn add_product(&mut self, product: Product) {
         let id = product.id;
 
-        for word in product.name.split_whitespace() {
+        for word in self.tokenize(&product.name) {
             self.name_index
-                .entry(word.to_lowercase())
+                .entry(word)
                 .or_insert_with(HashSet::new)
                 .insert(id);
         }
 
-        self.brand_index
-            .entry(product.brand.to_lowercase())
-            .or_insert_with(HashSet::new)
-            .insert(id);
+        let brand = normalize_term(&product.brand);
+        if brand.chars().count() >= self.min_term_length {
+            self.brand_index
+                .entry(brand)
+                .or_insert_with(HashSet::new)
+                .insert(id);
+        }
 
         self.category_index
             .entry(product.category.clone())
@@ -43,10 +280,19 @@ impl ProductIndex {
             .insert(id);
 
         for tag in &product.tags {
-            self.tag_index
-                .entry(tag.to_lowercase())
-                .or_insert_with(HashSet::new)
-                .insert(id);
+            let tag = normalize_term(tag);
+            let ids = self.tag_index.entry(tag.clone()).or_default();
+
+            #[cfg(feature = "tag-sketch")]
+            if ids.insert(id) {
+                self.tag_sketch.add(&tag, 1);
+            }
+            #[cfg(not(feature = "tag-sketch"))]
+            ids.insert(id);
+        }
+
+        for region in &product.restricted_regions {
+            self.region_index.entry(normalize_term(region)).or_default().insert(id);
         }
 
         self.products.insert(id, product);
@@ -56,12 +302,76 @@ impl ProductIndex {
         self.products.get(&id)
     }
 
+    pub fn set_active(&mut self, id: u64, active: bool) -> bool {
+        match self.products.get_mut(&id) {
+            Some(product) => {
+                product.active = active;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn reserve_stock(&mut self, id: u64, qty: u32) -> Result<(), StockError> {
+        self.products
+            .get_mut(&id)
+            .ok_or(StockError::UnknownProduct(id))?
+            .reserve_stock(qty)
+    }
+
+    pub fn release_stock(&mut self, id: u64, qty: u32) -> Result<(), StockError> {
+        self.products
+            .get_mut(&id)
+            .ok_or(StockError::UnknownProduct(id))?
+            .release_stock(qty);
+        Ok(())
+    }
+
+    pub fn analyze_query(&self, query: &str) -> Vec<String> {
+        self.tokenize(query)
+    }
+
+    /// Returns the closest indexed name, brand or tag term to `term`, or
+    /// `None` if the term is already known or nothing is close enough.
+    /// Terms of five or more characters tolerate two edits, shorter ones one.
+    pub fn correct_term(&self, term: &str) -> Option<String> {
+        let term = normalize_term(term);
+        let vocabulary = [&self.name_index, &self.brand_index, &self.tag_index];
+
+        if vocabulary.iter().any(|index| index.contains_key(&term)) {
+            return None;
+        }
+
+        let max_distance = if term.chars().count() >= 5 { 2 } else { 1 };
+        let mut best: Option<(usize, usize, &String)> = None;
+
+        for index in vocabulary {
+            for (candidate, ids) in index {
+                let distance = edit_distance(&term, candidate);
+                if distance > max_distance {
+                    continue;
+                }
+
+                // Prefer fewer edits, then more popular terms, then alphabetical order
+                let is_better = match best {
+                    None => true,
+                    Some((best_distance, best_usage, best_term)) => (distance, std::cmp::Reverse(ids.len()), candidate)
+                        < (best_distance, std::cmp::Reverse(best_usage), best_term),
+                };
+                if is_better {
+                    best = Some((distance, ids.len(), candidate));
+                }
+            }
+        }
+
+        best.map(|(_, _, candidate)| candidate.clone())
+    }
+
     pub fn search_by_name(&self, query: &str) -> Vec<u64> {
-        let query_lower = query.to_lowercase();
         let mut results = HashSet::new();
 
-        for word in query_lower.split_whitespace() {
-            if let Some(ids) = self.name_index.get(word) {
+        for word in self.analyze_query(query) {
+            if let Some(ids) = self.name_index.get(&word) {
                 results.extend(ids);
             }
         }
@@ -69,9 +379,31 @@ impl ProductIndex {
         results.into_iter().collect()
     }
 
+    /// Maps each product whose name has a token within `max_distance` edits
+    /// of a query token to the smallest such distance.
+    pub fn search_by_name_fuzzy(&self, query: &str, max_distance: usize) -> HashMap<u64, usize> {
+        let mut matches: HashMap<u64, usize> = HashMap::new();
+
+        for word in self.analyze_query(query) {
+            for (term, ids) in &self.name_index {
+                let distance = edit_distance(&word, term);
+                if distance > max_distance {
+                    continue;
+                }
+
+                for &id in ids {
+                    let best = matches.entry(id).or_insert(distance);
+                    *best = (*best).min(distance);
+                }
+            }
+        }
+
+        matches
+    }
+
     pub fn search_by_brand(&self, brand: &str) -> Vec<u64> {
         self.brand_index
-            .get(&brand.to_lowercase())
+            .get(&normalize_term(brand))
             .map(|ids| ids.iter().copied().collect())
             .unwrap_or_default()
     }
@@ -83,36 +415,451 @@ impl ProductIndex {
             .unwrap_or_default()
     }
 
+    /// Ids of products that may not be sold in `region`.
+    pub fn restricted_in(&self, region: &str) -> HashSet<u64> {
+        self.region_index
+            .get(&normalize_term(region))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn search_by_categories(&self, categories: &[Category]) -> Vec<u64> {
+        let mut results = HashSet::new();
+
+        for category in categories {
+            if let Some(ids) = self.category_index.get(category) {
+                results.extend(ids);
+            }
+        }
+
+        results.into_iter().collect()
+    }
+
+    pub fn search_by_category_text(&self, query: &str) -> Vec<u64> {
+        let query_lower = normalize_term(query);
+        let query_words: Vec<&str> = query_lower.split_whitespace().collect();
+        let mut results = HashSet::new();
+
+        for (category, ids) in &self.category_index {
+            let category_name = category.to_string().to_lowercase();
+            if category_name
+                .split_whitespace()
+                .any(|word| query_words.contains(&word))
+            {
+                results.extend(ids);
+            }
+        }
+
+        results.into_iter().collect()
+    }
+
     pub fn search_by_tag(&self, tag: &str) -> Vec<u64> {
         self.tag_index
-            .get(&tag.to_lowercase())
+            .get(&normalize_term(tag))
             .map(|ids| ids.iter().copied().collect())
             .unwrap_or_default()
     }
 
+    pub fn search_by_tag_prefix(&self, prefix: &str) -> Vec<u64> {
+        let prefix_lower = normalize_term(prefix);
+        let mut results = HashSet::new();
+
+        for (tag, ids) in &self.tag_index {
+            if tag.starts_with(&prefix_lower) {
+                results.extend(ids);
+            }
+        }
+
+        results.into_iter().collect()
+    }
+
+    pub fn brand_usage(&self, brand: &str) -> usize {
+        self.brand_index
+            .get(&normalize_term(brand))
+            .map(|ids| ids.len())
+            .unwrap_or(0)
+    }
+
+    pub fn tag_usage(&self, tag: &str) -> usize {
+        self.tag_index
+            .get(&normalize_term(tag))
+            .map(|ids| ids.len())
+            .unwrap_or(0)
+    }
+
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.tag_index.keys().cloned().collect();
+        tags.sort();
+        tags
+    }
+
+    /// Moves one product to `new_category`, updating only the category index.
+    /// Returns false if the product is unknown.
+    pub fn reassign_category(&mut self, id: u64, new_category: Category) -> bool {
+        let product = match self.products.get_mut(&id) {
+            Some(product) => product,
+            None => return false,
+        };
+
+        let old_category = std::mem::replace(&mut product.category, new_category.clone());
+        if old_category == new_category {
+            return true;
+        }
+
+        if let Some(ids) = self.category_index.get_mut(&old_category) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.category_index.remove(&old_category);
+            }
+        }
+        self.category_index.entry(new_category).or_default().insert(id);
+        true
+    }
+
+    /// Tags one product in place, updating only its `tag_index` entry.
+    /// Returns false if the product is unknown or already carries the tag.
+    pub fn add_tag(&mut self, id: u64, tag: String) -> bool {
+        let key = normalize_term(&tag);
+        let product = match self.products.get_mut(&id) {
+            Some(product) => product,
+            None => return false,
+        };
+        if key.is_empty() || product.tags.iter().any(|existing| normalize_term(existing) == key) {
+            return false;
+        }
+
+        product.tags.push(tag);
+        self.tag_index.entry(key.clone()).or_default().insert(id);
+        #[cfg(feature = "tag-sketch")]
+        self.tag_sketch.add(&key, 1);
+        true
+    }
+
+    /// Removes a tag from one product, pruning the tag's bucket once no
+    /// product carries it. Returns false if the product didn't have the tag.
+    pub fn remove_tag(&mut self, id: u64, tag: &str) -> bool {
+        let key = normalize_term(tag);
+        let product = match self.products.get_mut(&id) {
+            Some(product) => product,
+            None => return false,
+        };
+
+        let before = product.tags.len();
+        product.tags.retain(|existing| normalize_term(existing) != key);
+        if product.tags.len() == before {
+            return false;
+        }
+
+        if let Some(ids) = self.tag_index.get_mut(&key) {
+            ids.remove(&id);
+            if ids.is_empty() {
+                self.tag_index.remove(&key);
+            }
+        }
+        #[cfg(feature = "tag-sketch")]
+        self.tag_sketch.remove(&key, 1);
+        true
+    }
+
+    pub fn rename_tag(&mut self, from: &str, into: &str) -> usize {
+        let from_lower = normalize_term(from);
+        let into_lower = normalize_term(into);
+        if from_lower == into_lower {
+            return 0;
+        }
+
+        let ids = match self.tag_index.remove(&from_lower) {
+            Some(ids) => ids,
+            None => return 0,
+        };
+
+        for id in &ids {
+            if let Some(product) = self.products.get_mut(id) {
+                product.tags.retain(|tag| normalize_term(tag) != from_lower);
+                if !product.tags.iter().any(|tag| normalize_term(tag) == into_lower) {
+                    product.tags.push(into.to_string());
+                }
+            }
+        }
+
+        let count = ids.len();
+        #[cfg(feature = "tag-sketch")]
+        {
+            self.tag_sketch.remove(&from_lower, count as u64);
+            let existing = self.tag_index.get(&into_lower).map_or(0, |into_ids| into_ids.intersection(&ids).count());
+            self.tag_sketch.add(&into_lower, (count - existing) as u64);
+        }
+        self.tag_index.entry(into_lower).or_default().extend(ids);
+        count
+    }
+
+    pub fn tag_vector(&self, id: u64) -> HashMap<String, f64> {
+        let mut vector = HashMap::new();
+
+        if let Some(product) = self.products.get(&id) {
+            if product.tags.is_empty() {
+                return vector;
+            }
+
+            let weight = 1.0 / product.tags.len() as f64;
+            for tag in &product.tags {
+                *vector.entry(normalize_term(tag)).or_insert(0.0) += weight;
+            }
+        }
+
+        vector
+    }
+
+    pub fn verify_consistency(&self) -> Result<(), Vec<ConsistencyError>> {
+        let mut errors = Vec::new();
+
+        self.check_index("name", &self.name_index, |product| self.tokenize(&product.name), &mut errors);
+        self.check_index(
+            "brand",
+            &self.brand_index,
+            |product| {
+                if normalize_term(&product.brand).chars().count() >= self.min_term_length {
+                    vec![normalize_term(&product.brand)]
+                } else {
+                    Vec::new()
+                }
+            },
+            &mut errors,
+        );
+        self.check_index("category", &self.category_index, |product| vec![product.category.clone()], &mut errors);
+        self.check_index(
+            "tag",
+            &self.tag_index,
+            |product| product.tags.iter().map(|tag| normalize_term(tag)).collect(),
+            &mut errors,
+        );
+        self.check_index(
+            "region",
+            &self.region_index,
+            |product| product.restricted_regions.iter().map(|region| normalize_term(region)).collect(),
+            &mut errors,
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_index<K, F>(
+        &self,
+        index: &'static str,
+        buckets: &HashMap<K, HashSet<u64>>,
+        keys_for: F,
+        errors: &mut Vec<ConsistencyError>,
+    ) where
+        K: Eq + std::hash::Hash + fmt::Display,
+        F: Fn(&Product) -> Vec<K>,
+    {
+        for (key, ids) in buckets {
+            for &id in ids {
+                match self.products.get(&id) {
+                    None => errors.push(ConsistencyError::DanglingId {
+                        index,
+                        key: key.to_string(),
+                        id,
+                    }),
+                    Some(product) if !keys_for(product).contains(key) => {
+                        errors.push(ConsistencyError::StaleEntry {
+                            index,
+                            key: key.to_string(),
+                            id,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for product in self.products.values() {
+            for key in keys_for(product) {
+                let indexed = buckets.get(&key).is_some_and(|ids| ids.contains(&product.id));
+                if !indexed {
+                    errors.push(ConsistencyError::MissingEntry {
+                        index,
+                        key: key.to_string(),
+                        id: product.id,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn category_aggregates(&self) -> HashMap<Category, CategoryAggregate> {
+        let mut aggregates = HashMap::new();
+
+        for (category, ids) in &self.category_index {
+            let products: Vec<&Product> = ids.iter().filter_map(|id| self.products.get(id)).collect();
+            if products.is_empty() {
+                continue;
+            }
+
+            let count = products.len();
+            let total_price: f64 = products.iter().map(|p| p.price).sum();
+            let total_rating: f64 = products.iter().map(|p| p.rating as f64).sum();
+
+            aggregates.insert(
+                category.clone(),
+                CategoryAggregate {
+                    count,
+                    avg_price: total_price / count as f64,
+                    avg_rating: total_rating / count as f64,
+                    min_price: products.iter().map(|p| p.price).fold(f64::INFINITY, f64::min),
+                    max_price: products.iter().map(|p| p.price).fold(f64::NEG_INFINITY, f64::max),
+                },
+            );
+        }
+
+        aggregates
+    }
+
+    /// Full O(n) scan over every product in insertion order; prefer the
+    /// indexed lookups when one fits.
+    pub fn find<F>(&self, pred: F) -> Vec<&Product>
+    where
+        F: Fn(&Product) -> bool,
+    {
+        self.products.values().filter(|product| pred(product)).collect()
+    }
+
+    /// Full scan returning the ids, in insertion order, of products that fail
+    /// any of the enabled checks.
+    pub fn incomplete_products(&self, criteria: CompletenessCriteria) -> Vec<u64> {
+        self.products
+            .values()
+            .filter(|product| criteria.is_incomplete(product))
+            .map(|product| product.id)
+            .collect()
+    }
+
     pub fn all_products(&self) -> Vec<&Product> {
         self.products.values().collect()
     }
 
+    pub fn sorted_by<F, K>(&self, key: F) -> Vec<&Product>
+    where
+        F: Fn(&Product) -> K,
+        K: Ord,
+    {
+        let mut products: Vec<&Product> = self.products.values().collect();
+        products.sort_by_key(|product| key(product));
+        products
+    }
+
+    pub fn sorted_by_price(&self) -> Vec<&Product> {
+        self.sorted_by(|product| OrderedF64(product.price))
+    }
+
+    pub fn sorted_by_rating(&self) -> Vec<&Product> {
+        self.sorted_by(|product| OrderedF64(product.rating as f64))
+    }
+
+    pub fn sorted_by_bayesian_rating(&self, prior_mean: f32, prior_weight: f32) -> Vec<&Product> {
+        self.sorted_by(|product| OrderedF64(product.bayesian_rating(prior_mean, prior_weight) as f64))
+    }
+
+    /// Products with a non-finite price are left out; `None` if no finite
+    /// prices remain.
+    pub fn price_quantile(&self, q: f64) -> Option<f64> {
+        if !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let mut prices: Vec<f64> = self.products.values().map(|p| p.price).filter(|price| price.is_finite()).collect();
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort_by(f64::total_cmp);
+
+        // Linear interpolation between the two closest ranks
+        let position = q * (prices.len() - 1) as f64;
+        let lower = position.floor() as usize;
+        let upper = position.ceil() as usize;
+        let fraction = position - lower as f64;
+
+        Some(prices[lower] + (prices[upper] - prices[lower]) * fraction)
+    }
+
+    pub fn price_quartiles(&self) -> Option<(f64, f64, f64)> {
+        Some((
+            self.price_quantile(0.25)?,
+            self.price_quantile(0.5)?,
+            self.price_quantile(0.75)?,
+        ))
+    }
+
+    /// Writes every product as one JSON array, serializing and flushing one
+    /// element at a time so the catalog is never held in memory as a whole.
+    pub fn export_json<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(b"[")?;
+        for (i, product) in self.products.values().enumerate() {
+            if i > 0 {
+                w.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut w, product)?;
+            w.flush()?;
+        }
+        w.write_all(b"]")?;
+        w.flush()
+    }
+
+    /// Adds every product in a JSON array. The batch is all-or-nothing: a
+    /// parse failure, an invalid product or an id already in use (in the
+    /// index or earlier in the batch) rejects it before anything is added.
+    pub fn import_json<R: Read>(&mut self, r: R) -> Result<usize, MegastoreError> {
+        let products: Vec<Product> = serde_json::from_reader(r)?;
+        self.check_new_products(&products)?;
+        let count = products.len();
+        for product in products {
+            self.add_product(product);
+        }
+        Ok(count)
+    }
+
+    pub(crate) fn check_new_products(&self, products: &[Product]) -> Result<(), MegastoreError> {
+        let mut seen = HashSet::new();
+        for product in products {
+            product.validate()?;
+            if self.products.contains_key(&product.id) || !seen.insert(product.id) {
+                return Err(MegastoreError::DuplicateId(product.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Order-independent hash of every product's fields. Stable across
+    /// builds and toolchains, so it can be persisted and compared later.
+    pub fn content_hash(&self) -> u64 {
+        self.products
+            .values()
+            .fold(0, |hash, product| hash ^ hash_product(product))
+    }
+
     pub fn product_count(&self) -> usize {
         self.products.len()
     }
 
     pub fn remove_product(&mut self, id: u64) -> Option<Product> {
         if let Some(product) = self.products.shift_remove(&id) {
-            for word in product.name.split_whitespace() {
-                if let Some(ids) = self.name_index.get_mut(&word.to_lowercase()) {
+            for word in self.tokenize(&product.name) {
+                if let Some(ids) = self.name_index.get_mut(&word) {
                     ids.remove(&id);
                     if ids.is_empty() {
-                        self.name_index.remove(&word.to_lowercase());
+                        self.name_index.remove(&word);
                     }
                 }
             }
 
-            if let Some(ids) = self.brand_index.get_mut(&product.brand.to_lowercase()) {
+            if let Some(ids) = self.brand_index.get_mut(&normalize_term(&product.brand)) {
                 ids.remove(&id);
                 if ids.is_empty() {
-                    self.brand_index.remove(&product.brand.to_lowercase());
+                    self.brand_index.remove(&normalize_term(&product.brand));
                 }
             }
 
@@ -124,10 +871,24 @@ impl ProductIndex {
             }
 
             for tag in &product.tags {
-                if let Some(ids) = self.tag_index.get_mut(&tag.to_lowercase()) {
+                if let Some(ids) = self.tag_index.get_mut(&normalize_term(tag)) {
+                    #[cfg(feature = "tag-sketch")]
+                    if ids.contains(&id) {
+                        self.tag_sketch.remove(&normalize_term(tag), 1);
+                    }
+                    ids.remove(&id);
+                    if ids.is_empty() {
+                        self.tag_index.remove(&normalize_term(tag));
+                    }
+                }
+            }
+
+            for region in &product.restricted_regions {
+                let region = normalize_term(region);
+                if let Some(ids) = self.region_index.get_mut(&region) {
                     ids.remove(&id);
                     if ids.is_empty() {
-                        self.tag_index.remove(&tag.to_lowercase());
+                        self.region_index.remove(&region);
                     }
                 }
             }
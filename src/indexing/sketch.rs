@@ -0,0 +1,78 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Count-min sketch: a fixed `depth x width` grid of counters that estimates
+/// how often a key was added. Estimates never undercount while removals stay
+/// matched to earlier additions, and overcount by at most `epsilon * total`
+/// with probability `1 - delta`, where `total` is the sum of all counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u64>,
+    total: u64,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        let width = width.max(1);
+        let depth = depth.max(1);
+        CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            total: 0,
+        }
+    }
+
+    /// Sizes the grid for the given error bound: `width = ceil(e / epsilon)`
+    /// and `depth = ceil(ln(1 / delta))`.
+    pub fn with_error(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil() as usize;
+        Self::new(width, depth)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Sum of all counts currently held.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn add(&mut self, key: &str, count: u64) {
+        for row in 0..self.depth {
+            let slot = self.slot(row, key);
+            self.counters[slot] += count;
+        }
+        self.total += count;
+    }
+
+    /// Undoes an earlier `add`. Counters saturate at zero.
+    pub fn remove(&mut self, key: &str, count: u64) {
+        for row in 0..self.depth {
+            let slot = self.slot(row, key);
+            self.counters[slot] = self.counters[slot].saturating_sub(count);
+        }
+        self.total = self.total.saturating_sub(count);
+    }
+
+    pub fn estimate(&self, key: &str) -> u64 {
+        (0..self.depth)
+            .map(|row| self.counters[self.slot(row, key)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn slot(&self, row: usize, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        row * self.width + (hasher.finish() % self.width as u64) as usize
+    }
+}
@@ -1,8 +1,10 @@
+pub mod error;
 pub mod models;
 pub mod indexing;
 pub mod graph;
 pub mod search;
 
+pub use error::MegastoreError;
 pub use models::{Product, Category};
 pub use indexing::ProductIndex;
 pub use graph::RecommendationGraph;
@@ -145,11 +145,13 @@ fn main() {
     println!("\n🔍 Conexões do produto 1 (Notebook Dell):");
     let connections = graph.get_connections(1);
     for (product_id, weight, relation_type) in connections {
-        let relation_str = match relation_type {
+        let relation_str = match &relation_type {
             megastore_search::graph::RelationType::Similar => "Similar",
             megastore_search::graph::RelationType::BoughtTogether => "Comprado junto",
             megastore_search::graph::RelationType::SameCategory => "Mesma categoria",
             megastore_search::graph::RelationType::SameBrand => "Mesma marca",
+            megastore_search::graph::RelationType::AlsoViewed => "Também visto",
+            megastore_search::graph::RelationType::Custom(label) => label.as_str(),
         };
         println!("  → Produto {} | Peso: {:.2} | Tipo: {}", product_id, weight, relation_str);
     }
@@ -346,10 +348,10 @@ fn main() {
     println!("🔄 Busca Integrada com Recomendações");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 
-    search_engine.add_product_relation(1, 4, 0.85, megastore_search::graph::RelationType::Similar);
-    search_engine.add_product_relation(1, 2, 0.75, megastore_search::graph::RelationType::BoughtTogether);
-    search_engine.add_product_relation(2, 4, 0.5, megastore_search::graph::RelationType::SameCategory);
-    search_engine.add_product_relation(2, 3, 0.6, megastore_search::graph::RelationType::BoughtTogether);
+    search_engine.add_product_relation(1, 4, 0.85, megastore_search::graph::RelationType::Similar).expect("produto cadastrado");
+    search_engine.add_product_relation(1, 2, 0.75, megastore_search::graph::RelationType::BoughtTogether).expect("produto cadastrado");
+    search_engine.add_product_relation(2, 4, 0.5, megastore_search::graph::RelationType::SameCategory).expect("produto cadastrado");
+    search_engine.add_product_relation(2, 3, 0.6, megastore_search::graph::RelationType::BoughtTogether).expect("produto cadastrado");
     println!("✓ Relações entre produtos criadas no grafo");
 
     println!("\n🔍 Busca 'notebook' com recomendações:");
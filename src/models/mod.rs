@@ -1,3 +1,3 @@
 pub mod product;
 
-pub use product::{Product, Category};
\ No newline at end of file
+pub use product::{normalize_term, price_to_cents, Product, ProductComparison, ProductSummary, SearchFields, Category, StockError, Variant};
\ No newline at end of file
@@ -1,5 +1,9 @@
+use crate::error::MegastoreError;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Category {
@@ -30,6 +34,69 @@ impl fmt::Display for Category {
     }
 }
 
+impl FromStr for Category {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Electronics" => Category::Electronics,
+            "Clothing" => Category::Clothing,
+            "Food" => Category::Food,
+            "Home & Decor" => Category::HomeDecor,
+            "Books" => Category::Books,
+            "Sports" => Category::Sports,
+            "Toys" => Category::Toys,
+            "Beauty" => Category::Beauty,
+            other => Category::Other(other.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StockError {
+    InsufficientStock { requested: u32, available: u32 },
+    UnknownProduct(u64),
+}
+
+impl fmt::Display for StockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StockError::InsufficientStock { requested, available } => write!(
+                f,
+                "insufficient stock: requested {}, available {}",
+                requested, available
+            ),
+            StockError::UnknownProduct(id) => write!(f, "unknown product {}", id),
+        }
+    }
+}
+
+impl std::error::Error for StockError {}
+
+const WHOLE_WORD_NAME_BONUS: f64 = 4.0;
+const WHOLE_WORD_TAG_BONUS: f64 = 1.0;
+const FUZZY_NAME_BONUS: f64 = 10.0;
+
+fn has_whole_word(text: &str, word: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric())
+        .any(|candidate| candidate.to_lowercase() == word)
+}
+
+/// Lowercases `text` and collapses runs of whitespace into single spaces,
+/// trimming both ends. Index keys and lookups go through this so "  Dell "
+/// and "dell" land in the same bucket; stored product fields keep the
+/// original spelling for display.
+pub fn normalize_term(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn price_to_cents(amount: f64) -> u64 {
+    (amount * 100.0).round() as u64
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
     pub id: u64,
@@ -41,6 +108,121 @@ pub struct Product {
     pub tags: Vec<String>,
     pub rating: f32,
     pub stock: u32,
+    #[serde(default)]
+    pub review_count: u32,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default)]
+    pub sale_price: Option<f64>,
+    /// Quantity-break pricing as `(minimum quantity, unit price)` pairs.
+    #[serde(default)]
+    pub price_tiers: Vec<(u32, f64)>,
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+    /// Regions (e.g. "EU") where the product may not be sold. Compared
+    /// after `normalize_term`.
+    #[serde(default)]
+    pub restricted_regions: Vec<String>,
+}
+
+/// A purchasable option of a parent product, such as a size or color.
+/// Everything not listed here is shared with the parent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub id: u64,
+    pub attributes: HashMap<String, String>,
+    pub price_delta: f64,
+    pub stock: u32,
+}
+
+impl Variant {
+    pub fn matches_attributes(&self, attributes: &[(String, String)]) -> bool {
+        attributes.iter().all(|(key, value)| {
+            self.attributes
+                .get(key)
+                .is_some_and(|own| own.eq_ignore_ascii_case(value))
+        })
+    }
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchFields {
+    pub name: bool,
+    pub brand: bool,
+    pub description: bool,
+    pub tags: bool,
+    pub category: bool,
+}
+
+impl SearchFields {
+    pub fn all() -> Self {
+        SearchFields {
+            name: true,
+            brand: true,
+            description: true,
+            tags: true,
+            category: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        SearchFields {
+            name: false,
+            brand: false,
+            description: false,
+            tags: false,
+            category: false,
+        }
+    }
+
+    pub fn with_name(mut self) -> Self {
+        self.name = true;
+        self
+    }
+
+    pub fn with_brand(mut self) -> Self {
+        self.brand = true;
+        self
+    }
+
+    pub fn with_description(mut self) -> Self {
+        self.description = true;
+        self
+    }
+
+    pub fn with_tags(mut self) -> Self {
+        self.tags = true;
+        self
+    }
+
+    pub fn with_category(mut self) -> Self {
+        self.category = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProductSummary {
+    pub id: u64,
+    pub name: String,
+    pub brand: String,
+    pub price: f64,
+    pub rating: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductComparison {
+    pub price_difference: f64,
+    pub rating_difference: f32,
+    pub shared_tags: Vec<String>,
+    pub unique_tags: Vec<String>,
+    pub other_unique_tags: Vec<String>,
+    pub same_brand: bool,
+    pub same_category: bool,
 }
 
 impl Product {
@@ -62,6 +244,12 @@ impl Product {
             tags: Vec::new(),
             rating: 0.0,
             stock: 0,
+            review_count: 0,
+            active: true,
+            sale_price: None,
+            price_tiers: Vec::new(),
+            variants: Vec::new(),
+            restricted_regions: Vec::new(),
         }
     }
 
@@ -71,28 +259,217 @@ impl Product {
         }
     }
 
+    pub fn summary(&self) -> ProductSummary {
+        ProductSummary {
+            id: self.id,
+            name: self.name.clone(),
+            brand: self.brand.clone(),
+            price: self.price,
+            rating: self.rating,
+        }
+    }
+
+    /// Shrinks `rating` toward `prior_mean` as if the product had
+    /// `prior_weight` extra reviews at that mean, so ratings backed by few
+    /// reviews count for less.
+    pub fn bayesian_rating(&self, prior_mean: f32, prior_weight: f32) -> f32 {
+        let reviews = self.review_count as f32;
+        let total_weight = prior_weight + reviews;
+        if total_weight <= 0.0 {
+            return prior_mean;
+        }
+
+        (prior_weight * prior_mean + reviews * self.rating) / total_weight
+    }
+
+    pub fn compare(&self, other: &Product) -> ProductComparison {
+        let has_tag = |tags: &[String], tag: &str| tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+
+        let (shared_tags, unique_tags) = self
+            .tags
+            .iter()
+            .cloned()
+            .partition(|tag| has_tag(&other.tags, tag));
+
+        let other_unique_tags = other
+            .tags
+            .iter()
+            .filter(|tag| !has_tag(&self.tags, tag))
+            .cloned()
+            .collect();
+
+        ProductComparison {
+            price_difference: self.price - other.price,
+            rating_difference: self.rating - other.rating,
+            shared_tags,
+            unique_tags,
+            other_unique_tags,
+            same_brand: self.brand.eq_ignore_ascii_case(&other.brand),
+            same_category: self.category == other.category,
+        }
+    }
+
+    pub fn is_restricted_in(&self, region: &str) -> bool {
+        let region = normalize_term(region);
+        self.restricted_regions.iter().any(|restricted| normalize_term(restricted) == region)
+    }
+
+    /// Checks the fields a catalog entry can't do without: a name, a finite
+    /// non-negative price and sale price, and a rating within 0-5.
+    pub fn validate(&self) -> Result<(), MegastoreError> {
+        let invalid = |reason: &str| {
+            Err(MegastoreError::InvalidProduct {
+                id: self.id,
+                reason: reason.to_string(),
+            })
+        };
+
+        if self.name.trim().is_empty() {
+            return invalid("name is empty");
+        }
+        if !self.price.is_finite() || self.price < 0.0 {
+            return invalid("price must be a non-negative number");
+        }
+        if self.sale_price.is_some_and(|sale| !sale.is_finite() || sale < 0.0) {
+            return invalid("sale price must be a non-negative number");
+        }
+        if !(0.0..=5.0).contains(&self.rating) {
+            return invalid("rating must be between 0 and 5");
+        }
+        Ok(())
+    }
+
+    /// True if the product itself or any of its variants has stock.
+    pub fn has_stock(&self) -> bool {
+        self.stock > 0 || self.variants.iter().any(|variant| variant.stock > 0)
+    }
+
+    pub fn matching_variants(&self, attributes: &[(String, String)], in_stock_only: bool) -> Vec<&Variant> {
+        self.variants
+            .iter()
+            .filter(|variant| variant.matches_attributes(attributes))
+            .filter(|variant| !in_stock_only || variant.stock > 0)
+            .collect()
+    }
+
+    pub fn price_for_quantity(&self, qty: u32) -> f64 {
+        self.price_tiers
+            .iter()
+            .filter(|(threshold, _)| *threshold <= qty)
+            .max_by_key(|(threshold, _)| *threshold)
+            .map(|(_, price)| *price)
+            .unwrap_or(self.price)
+    }
+
+    pub fn is_on_sale(&self) -> bool {
+        self.sale_price
+            .is_some_and(|sale_price| price_to_cents(sale_price) < self.price_cents())
+    }
+
+    pub fn discount_percent(&self) -> Option<f64> {
+        if !self.is_on_sale() || self.price <= 0.0 {
+            return None;
+        }
+
+        self.sale_price
+            .map(|sale_price| (self.price - sale_price) / self.price * 100.0)
+    }
+
+    pub fn price_cents(&self) -> u64 {
+        price_to_cents(self.price)
+    }
+
+    pub fn format_price(&self, currency: &str) -> String {
+        let cents = self.price_cents();
+        format!("{} {}.{:02}", currency, cents / 100, cents % 100)
+    }
+
+    pub fn reserve_stock(&mut self, qty: u32) -> Result<(), StockError> {
+        if qty > self.stock {
+            return Err(StockError::InsufficientStock {
+                requested: qty,
+                available: self.stock,
+            });
+        }
+
+        self.stock -= qty;
+        Ok(())
+    }
+
+    pub fn release_stock(&mut self, qty: u32) {
+        self.stock = self.stock.saturating_add(qty);
+    }
+
     pub fn search_score(&self, query: &str) -> f64 {
-        let query_lower = query.to_lowercase();
+        self.search_score_fields(query, &SearchFields::all())
+    }
+
+    /// `search_score` plus a name bonus for a fuzzy match `distance` edits
+    /// away from the query, worth `10 / (1 + distance)` before the rating
+    /// boost, so an exact spelling always beats a near miss.
+    pub fn fuzzy_search_score(&self, query: &str, distance: usize) -> f64 {
+        let bonus = FUZZY_NAME_BONUS / (1 + distance) as f64;
+        self.search_score(query) + bonus * (1.0 + self.rating as f64 / 10.0)
+    }
+
+    /// `fuzzy_search_score` before the rating multiplier is applied.
+    pub fn fuzzy_relevance(&self, query: &str, distance: usize) -> f64 {
+        self.text_relevance(query, &SearchFields::all(), 1.0) + FUZZY_NAME_BONUS / (1 + distance) as f64
+    }
+
+    pub fn search_score_fields(&self, query: &str, fields: &SearchFields) -> f64 {
+        self.search_score_with_brand_weight(query, fields, 1.0)
+    }
+
+    pub fn search_score_with_brand_weight(&self, query: &str, fields: &SearchFields, brand_weight: f64) -> f64 {
+        self.text_relevance(query, fields, brand_weight) * self.rating_multiplier()
+    }
+
+    /// Field-match score before the rating multiplier is applied.
+    pub fn text_relevance(&self, query: &str, fields: &SearchFields, brand_weight: f64) -> f64 {
+        let query_lower = normalize_term(query);
         let mut score = 0.0;
 
-        if self.name.to_lowercase().contains(&query_lower) {
+        if fields.name && normalize_term(&self.name).contains(&query_lower) {
             score += 10.0;
         }
 
-        if self.brand.to_lowercase().contains(&query_lower) {
-            score += 5.0;
+        // A query token standing alone as a word beats the same letters
+        // buried inside a longer word ("pro" in "Laptop Pro" vs "Professional")
+        for token in query_lower.split_whitespace() {
+            if fields.name && has_whole_word(&self.name, token) {
+                score += WHOLE_WORD_NAME_BONUS;
+            }
+
+            if fields.tags && self.tags.iter().any(|tag| has_whole_word(tag, token)) {
+                score += WHOLE_WORD_TAG_BONUS;
+            }
         }
 
-        if self.description.to_lowercase().contains(&query_lower) {
+        if fields.brand && normalize_term(&self.brand).contains(&query_lower) {
+            score += 5.0 * brand_weight;
+        }
+
+        if fields.description && normalize_term(&self.description).contains(&query_lower) {
             score += 2.0;
         }
 
-        for tag in &self.tags {
-            if tag.to_lowercase().contains(&query_lower) {
-                score += 3.0;
+        if fields.tags {
+            for tag in &self.tags {
+                if normalize_term(tag).contains(&query_lower) {
+                    score += 3.0;
+                }
             }
         }
 
-        score * (1.0 + self.rating as f64 / 10.0)
+        if fields.category && normalize_term(&self.category.to_string()).contains(&query_lower) {
+            score += 1.5;
+        }
+
+        score
     }
-}
\ No newline at end of file
+
+    pub fn rating_multiplier(&self) -> f64 {
+        1.0 + self.rating as f64 / 10.0
+    }
+}
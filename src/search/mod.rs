@@ -1,16 +1,61 @@
-use crate::models::{Product, Category};
-use crate::indexing::ProductIndex;
-use crate::graph::RecommendationGraph;
-use std::collections::HashSet;
+pub mod observer;
+pub mod overrides;
+pub mod query;
+pub mod scoring;
+
+pub use observer::{NoopObserver, SearchObserver};
+pub use overrides::SearchOverrides;
+pub use query::{sort_results, SearchPage, SearchQueryBuilder, SortBy, SortKey};
+pub use scoring::{ScoreBreakdown, ScoreScale, ScoringConfig};
+
+pub use crate::models::SearchFields;
+
+use crate::error::MegastoreError;
+use crate::models::{normalize_term, price_to_cents, Product, ProductSummary, Category, StockError, Variant};
+use crate::indexing::{edit_distance, Fnv1a, ProductIndex};
+use crate::graph::{RecommendationGraph, RelationType};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct SearchResult {
     pub product: Product,
     pub score: f64,
     pub match_type: MatchType,
+    /// Populated only when the engine was built `with_debug_scoring(true)`,
+    /// and only by text searches.
+    pub score_components: Option<ScoreBreakdown>,
 }
 
-#[derive(Debug, Clone)]
+impl SearchResult {
+    pub fn normalized_score(&self, scale: ScoreScale) -> f64 {
+        scale.normalize(self.score)
+    }
+}
+
+struct ScoredProduct<'a> {
+    product: &'a Product,
+    score: f64,
+    match_type: MatchType,
+    components: Option<ScoreBreakdown>,
+}
+
+impl ScoredProduct<'_> {
+    fn into_result(self) -> SearchResult {
+        SearchResult {
+            product: self.product.clone(),
+            score: self.score,
+            match_type: self.match_type,
+            score_components: self.components,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum MatchType {
     ExactName,
     PartialName,
@@ -21,15 +66,41 @@ pub enum MatchType {
     Combined,
 }
 
-#[derive(Debug, Clone)]
+pub fn filter_by_match_type(results: Vec<SearchResult>, types: &[MatchType]) -> Vec<SearchResult> {
+    results
+        .into_iter()
+        .filter(|result| types.contains(&result.match_type))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SearchFilters {
     pub min_price: Option<f64>,
     pub max_price: Option<f64>,
     pub min_rating: Option<f32>,
     pub category: Option<Category>,
+    pub categories: Vec<Category>,
     pub brand: Option<String>,
+    /// Compare `brand` exactly instead of ignoring case. Whitespace is still
+    /// collapsed either way.
+    pub case_sensitive_brand: bool,
+    /// Any-of match on whole tags, ignoring case; the same rule as
+    /// `ProductIndex::search_by_tag`.
     pub tags: Vec<String>,
     pub in_stock_only: bool,
+    pub min_stock: Option<u32>,
+    pub max_stock: Option<u32>,
+    pub on_sale_only: bool,
+    /// Variant attributes (e.g. `("size", "M")`) that at least one variant
+    /// must carry; combined with `in_stock_only`, that variant must have stock.
+    pub variant_attributes: Vec<(String, String)>,
+    /// Order quantity used to resolve tier pricing for the price range.
+    /// Without it the base `price` is compared.
+    pub quantity: Option<u32>,
+    pub exclude_ids: HashSet<u64>,
+    pub include_inactive: bool,
+    /// Shopper's region; products restricted there are excluded.
+    pub region: Option<String>,
 }
 
 impl SearchFilters {
@@ -39,9 +110,19 @@ impl SearchFilters {
             max_price: None,
             min_rating: None,
             category: None,
+            categories: Vec::new(),
             brand: None,
+            case_sensitive_brand: false,
             tags: Vec::new(),
             in_stock_only: false,
+            min_stock: None,
+            max_stock: None,
+            on_sale_only: false,
+            variant_attributes: Vec::new(),
+            quantity: None,
+            exclude_ids: HashSet::new(),
+            include_inactive: false,
+            region: None,
         }
     }
 
@@ -61,6 +142,11 @@ impl SearchFilters {
         self
     }
 
+    pub fn categories(mut self, categories: Vec<Category>) -> Self {
+        self.categories = categories;
+        self
+    }
+
     pub fn brand(mut self, brand: String) -> Self {
         self.brand = Some(brand);
         self
@@ -76,15 +162,212 @@ impl SearchFilters {
         self
     }
 
+    pub fn min_stock(mut self, stock: u32) -> Self {
+        self.min_stock = Some(stock);
+        self
+    }
+
+    pub fn max_stock(mut self, stock: u32) -> Self {
+        self.max_stock = Some(stock);
+        self
+    }
+
+    pub fn on_sale_only(mut self) -> Self {
+        self.on_sale_only = true;
+        self
+    }
+
+    pub fn variant_attribute(mut self, key: String, value: String) -> Self {
+        self.variant_attributes.push((key, value));
+        self
+    }
+
+    pub fn quantity(mut self, qty: u32) -> Self {
+        self.quantity = Some(qty);
+        self
+    }
+
+    pub fn exclude_ids(mut self, ids: HashSet<u64>) -> Self {
+        self.exclude_ids = ids;
+        self
+    }
+
+    pub fn include_inactive(mut self) -> Self {
+        self.include_inactive = true;
+        self
+    }
+
+    pub fn region(mut self, region: String) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn case_sensitive_brand(mut self) -> Self {
+        self.case_sensitive_brand = true;
+        self
+    }
+
+    pub fn to_query_string(&self) -> String {
+        let mut pairs = Vec::new();
+
+        if self.min_price.is_some() || self.max_price.is_some() {
+            let min = self.min_price.map(|p| p.to_string()).unwrap_or_default();
+            let max = self.max_price.map(|p| p.to_string()).unwrap_or_default();
+            pairs.push(format!("price={}-{}", min, max));
+        }
+
+        if let Some(rating) = self.min_rating {
+            pairs.push(format!("rating={}", rating));
+        }
+
+        if let Some(ref category) = self.category {
+            pairs.push(format!("cat={}", encode_component(&category.to_string())));
+        }
+
+        for category in &self.categories {
+            pairs.push(format!("anycat={}", encode_component(&category.to_string())));
+        }
+
+        if let Some(ref brand) = self.brand {
+            pairs.push(format!("brand={}", encode_component(brand)));
+        }
+
+        if self.case_sensitive_brand {
+            pairs.push("brandcase=1".to_string());
+        }
+
+        for tag in &self.tags {
+            pairs.push(format!("tag={}", encode_component(tag)));
+        }
+
+        if self.in_stock_only {
+            pairs.push("instock=1".to_string());
+        }
+
+        if self.min_stock.is_some() || self.max_stock.is_some() {
+            let min = self.min_stock.map(|s| s.to_string()).unwrap_or_default();
+            let max = self.max_stock.map(|s| s.to_string()).unwrap_or_default();
+            pairs.push(format!("stock={}-{}", min, max));
+        }
+
+        if self.on_sale_only {
+            pairs.push("onsale=1".to_string());
+        }
+
+        for (key, value) in &self.variant_attributes {
+            pairs.push(format!("variant={}:{}", encode_component(key), encode_component(value)));
+        }
+
+        if let Some(qty) = self.quantity {
+            pairs.push(format!("qty={}", qty));
+        }
+
+        if let Some(ref region) = self.region {
+            pairs.push(format!("region={}", encode_component(region)));
+        }
+
+        if !self.exclude_ids.is_empty() {
+            let mut ids: Vec<u64> = self.exclude_ids.iter().copied().collect();
+            ids.sort_unstable();
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            pairs.push(format!("exclude={}", ids.join(",")));
+        }
+
+        if self.include_inactive {
+            pairs.push("inactive=1".to_string());
+        }
+
+        pairs.join("&")
+    }
+
+    pub fn from_query_string(s: &str) -> Result<Self, FilterParseError> {
+        let mut filters = SearchFilters::new();
+
+        for pair in s.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, raw_value) = pair
+                .split_once('=')
+                .ok_or_else(|| FilterParseError::Malformed(pair.to_string()))?;
+            let invalid = || FilterParseError::InvalidValue {
+                key: key.to_string(),
+                value: raw_value.to_string(),
+            };
+            let value = decode_component(raw_value).ok_or_else(invalid)?;
+
+            match key {
+                "price" => {
+                    let (min, max) = value.split_once('-').ok_or_else(invalid)?;
+                    if !min.is_empty() {
+                        filters.min_price = Some(min.parse().map_err(|_| invalid())?);
+                    }
+                    if !max.is_empty() {
+                        filters.max_price = Some(max.parse().map_err(|_| invalid())?);
+                    }
+                }
+                "rating" => filters.min_rating = Some(value.parse().map_err(|_| invalid())?),
+                "cat" => filters.category = Some(value.parse().map_err(|_| invalid())?),
+                "anycat" => filters.categories.push(value.parse().map_err(|_| invalid())?),
+                "brand" => filters.brand = Some(value),
+                "tag" => filters.tags.push(value),
+                "instock" => filters.in_stock_only = value == "1",
+                "stock" => {
+                    let (min, max) = value.split_once('-').ok_or_else(invalid)?;
+                    if !min.is_empty() {
+                        filters.min_stock = Some(min.parse().map_err(|_| invalid())?);
+                    }
+                    if !max.is_empty() {
+                        filters.max_stock = Some(max.parse().map_err(|_| invalid())?);
+                    }
+                }
+                "exclude" => {
+                    for id in value.split(',').filter(|id| !id.is_empty()) {
+                        filters.exclude_ids.insert(id.parse().map_err(|_| invalid())?);
+                    }
+                }
+                "variant" => {
+                    // Split before decoding so an encoded ':' inside the key stays put
+                    let (key, attribute) = raw_value.split_once(':').ok_or_else(invalid)?;
+                    let key = decode_component(key).ok_or_else(invalid)?;
+                    let attribute = decode_component(attribute).ok_or_else(invalid)?;
+                    filters.variant_attributes.push((key, attribute));
+                }
+                "qty" => filters.quantity = Some(value.parse().map_err(|_| invalid())?),
+                "onsale" => filters.on_sale_only = value == "1",
+                "inactive" => filters.include_inactive = value == "1",
+                "region" => filters.region = Some(value),
+                "brandcase" => filters.case_sensitive_brand = value == "1",
+                _ => return Err(FilterParseError::UnknownKey(key.to_string())),
+            }
+        }
+
+        Ok(filters)
+    }
+
     fn matches(&self, product: &Product) -> bool {
+        if !self.include_inactive && !product.active {
+            return false;
+        }
+
+        if self.exclude_ids.contains(&product.id) {
+            return false;
+        }
+
+        if self.region.as_deref().is_some_and(|region| product.is_restricted_in(region)) {
+            return false;
+        }
+
+        let price_cents = match self.quantity {
+            Some(qty) => price_to_cents(product.price_for_quantity(qty)),
+            None => product.price_cents(),
+        };
+
         if let Some(min_price) = self.min_price {
-            if product.price < min_price {
+            if price_cents < price_to_cents(min_price) {
                 return false;
             }
         }
 
         if let Some(max_price) = self.max_price {
-            if product.price > max_price {
+            if price_cents > price_to_cents(max_price) {
                 return false;
             }
         }
@@ -101,20 +384,42 @@ impl SearchFilters {
             }
         }
 
+        if !self.categories.is_empty() && !self.categories.contains(&product.category) {
+            return false;
+        }
+
         if let Some(ref brand) = self.brand {
-            if product.brand.to_lowercase() != brand.to_lowercase() {
+            if !brand_equals(&product.brand, brand, self.case_sensitive_brand) {
                 return false;
             }
         }
 
-        if self.in_stock_only && product.stock == 0 {
+        if self.in_stock_only && !product.has_stock() {
+            return false;
+        }
+
+        if !self.variant_attributes.is_empty()
+            && product.matching_variants(&self.variant_attributes, self.in_stock_only).is_empty()
+        {
+            return false;
+        }
+
+        if self.min_stock.is_some_and(|min| product.stock < min) {
+            return false;
+        }
+
+        if self.max_stock.is_some_and(|max| product.stock > max) {
+            return false;
+        }
+
+        if self.on_sale_only && !product.is_on_sale() {
             return false;
         }
 
         if !self.tags.is_empty() {
             let has_any_tag = self.tags.iter().any(|tag| {
                 product.tags.iter().any(|product_tag| {
-                    product_tag.to_lowercase().contains(&tag.to_lowercase())
+                    normalize_term(product_tag) == normalize_term(tag)
                 })
             });
             if !has_any_tag {
@@ -126,9 +431,116 @@ impl SearchFilters {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterParseError {
+    UnknownKey(String),
+    InvalidValue { key: String, value: String },
+    Malformed(String),
+}
+
+impl fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterParseError::UnknownKey(key) => write!(f, "unknown filter key '{}'", key),
+            FilterParseError::InvalidValue { key, value } => {
+                write!(f, "invalid value '{}' for filter '{}'", value, key)
+            }
+            FilterParseError::Malformed(pair) => write!(f, "malformed filter pair '{}'", pair),
+        }
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+/// Former name of `MegastoreError`, kept so existing matches on
+/// `SearchError::UnknownProduct` still compile.
+pub type SearchError = MegastoreError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelationIssue {
+    /// A `Similar` edge joins products from different categories.
+    SimilarAcrossCategories { product_id_1: u64, product_id_2: u64 },
+    /// A `SameBrand` edge joins products whose brands differ.
+    BrandMismatch { product_id_1: u64, product_id_2: u64 },
+    /// A `SameCategory` edge joins products whose categories differ.
+    CategoryMismatch { product_id_1: u64, product_id_2: u64 },
+    /// An edge endpoint has no product in the index.
+    MissingProduct { product_id: u64 },
+}
+
+fn brand_equals(product_brand: &str, brand: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        product_brand.split_whitespace().eq(brand.split_whitespace())
+    } else {
+        normalize_term(product_brand) == normalize_term(brand)
+    }
+}
+
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_component(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+const MIN_RESULTS_BEFORE_CORRECTION: usize = 1;
+
+const CATEGORY_REPEAT_PENALTY: f64 = 0.5;
+
+const TRENDING_DAMPING: f64 = 0.85;
+const TRENDING_MAX_ITERATIONS: usize = 100;
+
+/// SplitMix64 step, mapped to a float in (0, 1]. Small and stable across
+/// platforms, which is all `search_sample` needs from a PRNG.
+fn next_unit(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+}
+
+fn default_hybrid_combine(base_score: f64, rec_score: f32) -> f64 {
+    base_score * 0.5 + rec_score as f64 * 0.5
+}
+
 pub struct SearchEngine {
     index: ProductIndex,
     graph: RecommendationGraph,
+    observer: Option<Box<dyn SearchObserver>>,
+    scoring: ScoringConfig,
+    history: Mutex<VecDeque<String>>,
+    history_capacity: usize,
+    overrides: SearchOverrides,
+    query_overrides: HashMap<String, SearchOverrides>,
+    case_sensitive_brand: bool,
+    debug_scoring: bool,
+    suppressed: HashSet<u64>,
+    synonyms: HashMap<String, Vec<String>>,
+    default_category: Option<Category>,
 }
 
 impl SearchEngine {
@@ -136,6 +548,136 @@ impl SearchEngine {
         SearchEngine {
             index: ProductIndex::new(),
             graph: RecommendationGraph::new(),
+            observer: None,
+            scoring: ScoringConfig::new(),
+            history: Mutex::new(VecDeque::new()),
+            history_capacity: 0,
+            overrides: SearchOverrides::new(),
+            query_overrides: HashMap::new(),
+            case_sensitive_brand: false,
+            debug_scoring: false,
+            suppressed: HashSet::new(),
+            synonyms: HashMap::new(),
+            default_category: None,
+        }
+    }
+
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    pub fn recent_queries(&self) -> Vec<String> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn record_query(&self, query: &str) {
+        let query = query.trim();
+        if self.history_capacity == 0 || query.is_empty() {
+            return;
+        }
+
+        let mut history = self.history.lock().unwrap();
+        history.retain(|previous| previous != query);
+        history.push_front(query.to_string());
+        history.truncate(self.history_capacity);
+    }
+
+    pub fn with_scoring(mut self, scoring: ScoringConfig) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    /// Restricts every search to `category` unless the caller filters by
+    /// category explicitly. Recommendations are restricted to it as well.
+    pub fn with_default_category(mut self, category: Category) -> Self {
+        self.default_category = Some(category);
+        self
+    }
+
+    /// Attach a `ScoreBreakdown` to text search results. Off by default to
+    /// skip the extra work on normal requests.
+    pub fn with_debug_scoring(mut self, enabled: bool) -> Self {
+        self.debug_scoring = enabled;
+        self
+    }
+
+    fn stock_factor(&self, product: &Product) -> f64 {
+        if product.stock == 0 {
+            self.scoring.out_of_stock_factor
+        } else {
+            1.0
+        }
+    }
+
+    fn adjust_score(&self, product: &Product, score: f64) -> f64 {
+        score * self.stock_factor(product)
+    }
+
+    fn brand_weight(&self, product: &Product) -> f64 {
+        if self.scoring.brand_idf {
+            let brand_size = self.index.brand_usage(&product.brand).max(1);
+            (self.index.product_count() as f64 / brand_size as f64).ln() + 1.0
+        } else {
+            1.0
+        }
+    }
+
+    fn query_score(&self, product: &Product, query: &str, fields: &SearchFields) -> f64 {
+        product.search_score_with_brand_weight(query, fields, self.brand_weight(product))
+    }
+
+    fn score_components(&self, product: &Product, query: &str, fields: &SearchFields) -> Option<ScoreBreakdown> {
+        if !self.debug_scoring {
+            return None;
+        }
+
+        let brand_weight = self.brand_weight(product);
+        Some(ScoreBreakdown {
+            relevance: product.text_relevance(query, fields, brand_weight),
+            rating_multiplier: product.rating_multiplier(),
+            brand_weight,
+            stock_factor: self.stock_factor(product),
+        })
+    }
+
+    /// Makes `search_by_brand` treat "Apple" and "apple" as different brands.
+    /// Filters opt in separately through `SearchFilters::case_sensitive_brand`.
+    pub fn with_case_sensitive_brand(mut self, enabled: bool) -> Self {
+        self.case_sensitive_brand = enabled;
+        self
+    }
+
+    /// Overrides applied to every text query without its own entry.
+    pub fn with_overrides(mut self, overrides: SearchOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Overrides for one query, matched after normalization. Replaces any
+    /// previous entry for the same query.
+    pub fn set_query_overrides(&mut self, query: &str, overrides: SearchOverrides) {
+        self.query_overrides.insert(normalize_term(query), overrides);
+    }
+
+    pub fn clear_query_overrides(&mut self, query: &str) -> Option<SearchOverrides> {
+        self.query_overrides.remove(&normalize_term(query))
+    }
+
+    fn overrides_for(&self, query: &str) -> &SearchOverrides {
+        self.query_overrides
+            .get(&normalize_term(query))
+            .unwrap_or(&self.overrides)
+    }
+
+    pub fn with_observer(mut self, observer: Box<dyn SearchObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    fn notify_search(&self, query: &str, result_count: usize, started: Instant) {
+        if let Some(ref observer) = self.observer {
+            observer.on_search(query, result_count, started.elapsed());
         }
     }
 
@@ -147,71 +689,613 @@ impl SearchEngine {
         self.index.add_product(product);
     }
 
-    pub fn add_product_relation(&mut self, product_id_1: u64, product_id_2: u64, weight: f32, relation_type: crate::graph::RelationType) {
-        self.graph.add_edge(product_id_1, product_id_2, weight, relation_type);
+    pub fn upsert_product(&mut self, product: Product) -> Option<Product> {
+        let product_id = product.id;
+        self.graph.add_product(product_id, product.category.to_string());
+        self.graph.set_category(product_id, product.category.to_string());
+
+        if self.index.get_product(product_id).is_some() {
+            self.index.update_product(product_id, product)
+        } else {
+            self.index.add_product(product);
+            None
+        }
+    }
+
+    /// Checked counterpart of `add_product`: rejects products that fail
+    /// `Product::validate` or reuse an id instead of inserting them.
+    pub fn try_add_product(&mut self, product: Product) -> Result<(), MegastoreError> {
+        self.index.check_new_products(std::slice::from_ref(&product))?;
+        self.add_product(product);
+        Ok(())
+    }
+
+    pub fn add_product_relation(&mut self, product_id_1: u64, product_id_2: u64, weight: f32, relation_type: crate::graph::RelationType) -> Result<(), MegastoreError> {
+        for id in [product_id_1, product_id_2] {
+            if self.index.get_product(id).is_none() {
+                return Err(MegastoreError::UnknownProduct(id));
+            }
+        }
+
+        if product_id_1 == product_id_2 {
+            return Err(MegastoreError::GraphError(format!("product {} cannot be related to itself", product_id_1)));
+        }
+        if !weight.is_finite() || weight < 0.0 {
+            return Err(MegastoreError::GraphError(format!("relation weight {} must be a non-negative number", weight)));
+        }
+
+        if !self.graph.add_edge(product_id_1, product_id_2, weight, relation_type) {
+            return Err(MegastoreError::GraphError(format!(
+                "products {} and {} are not both in the graph",
+                product_id_1, product_id_2
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn deactivate_product(&mut self, product_id: u64) -> bool {
+        self.index.set_active(product_id, false)
+    }
+
+    pub fn set_active_by_filter(&mut self, filters: &SearchFilters, active: bool) -> usize {
+        // Reactivation has to see inactive products, so match regardless of state
+        let filters = filters.clone().include_inactive();
+        let to_change: Vec<u64> = self
+            .index
+            .all_products()
+            .into_iter()
+            .filter(|product| product.active != active && filters.matches(product))
+            .map(|product| product.id)
+            .collect();
+
+        for &id in &to_change {
+            self.index.set_active(id, active);
+        }
+
+        to_change.len()
+    }
+
+    fn visible_product(&self, id: u64) -> Option<&Product> {
+        self.index.get_product(id).filter(|product| product.active)
+    }
+
+    /// Keeps a product out of every recommendation list while leaving it
+    /// searchable. Returns false if it was already suppressed.
+    pub fn suppress_from_recommendations(&mut self, id: u64) -> bool {
+        self.suppressed.insert(id)
+    }
+
+    pub fn unsuppress_from_recommendations(&mut self, id: u64) -> bool {
+        self.suppressed.remove(&id)
+    }
+
+    pub fn is_suppressed_from_recommendations(&self, id: u64) -> bool {
+        self.suppressed.contains(&id)
+    }
+
+    fn searchable_product(&self, id: u64) -> Option<&Product> {
+        self.visible_product(id).filter(|product| self.in_default_category(product))
+    }
+
+    fn in_default_category(&self, product: &Product) -> bool {
+        self.default_category
+            .as_ref()
+            .is_none_or(|category| product.category == *category)
+    }
+
+    fn recommendable_product(&self, id: u64) -> Option<&Product> {
+        self.visible_product(id)
+            .filter(|product| !self.suppressed.contains(&product.id))
+            .filter(|product| self.in_default_category(product))
+    }
+
+    /// Adds a `Similar` edge, weighted by the Jaccard similarity of their tag
+    /// sets, between every pair of products whose similarity exceeds
+    /// `min_similarity`. Pairs that are already connected are left alone.
+    pub fn auto_link_similar(&mut self, min_similarity: f32) -> usize {
+        let tag_sets: HashMap<u64, HashSet<String>> = self
+            .index
+            .all_products()
+            .into_iter()
+            .map(|product| (product.id, product.tags.iter().map(|tag| normalize_term(tag)).collect()))
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (&id, tags) in &tag_sets {
+            let candidates: HashSet<u64> = tags
+                .iter()
+                .flat_map(|tag| self.index.search_by_tag(tag))
+                .filter(|&other| other > id)
+                .collect();
+
+            for other in candidates {
+                let other_tags = &tag_sets[&other];
+                let shared = tags.intersection(other_tags).count();
+                let union = tags.union(other_tags).count();
+                let similarity = shared as f32 / union as f32;
+
+                if similarity > min_similarity && !self.graph.has_edge(id, other) {
+                    pairs.push((id, other, similarity));
+                }
+            }
+        }
+
+        for &(id, other, similarity) in &pairs {
+            self.graph.connect_similar_products(id, other, similarity);
+        }
+
+        pairs.len()
+    }
+
+    pub fn reserve(&mut self, product_id: u64, qty: u32) -> Result<(), StockError> {
+        self.index.reserve_stock(product_id, qty)
+    }
+
+    pub fn release(&mut self, product_id: u64, qty: u32) -> Result<(), StockError> {
+        self.index.release_stock(product_id, qty)
     }
 
     pub fn basic_search(&self, query: &str) -> Vec<SearchResult> {
+        let started = Instant::now();
+        self.record_query(query);
+        let results = self.run_basic_search(query);
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    fn run_basic_search(&self, query: &str) -> Vec<SearchResult> {
+        self.rank_basic(query)
+            .into_iter()
+            .map(ScoredProduct::into_result)
+            .collect()
+    }
+
+    /// Makes text searches for either term also match products found by the
+    /// other. Both terms are normalized; the link works in both directions.
+    pub fn add_synonym(&mut self, term: &str, synonym: &str) {
+        let (term, synonym) = (normalize_term(term), normalize_term(synonym));
+        if term.is_empty() || synonym.is_empty() || term == synonym {
+            return;
+        }
+
+        for (from, to) in [(&term, &synonym), (&synonym, &term)] {
+            let entry = self.synonyms.entry(from.clone()).or_default();
+            if !entry.contains(to) {
+                entry.push(to.clone());
+            }
+        }
+    }
+
+    fn rank_basic(&self, query: &str) -> Vec<ScoredProduct<'_>> {
+        let mut results = self.rank_term(query);
+
+        if let Some(synonyms) = self.synonyms.get(&normalize_term(query)) {
+            let mut seen: HashSet<u64> = results.iter().map(|r| r.product.id).collect();
+            for synonym in synonyms {
+                for scored in self.rank_term(synonym) {
+                    if seen.insert(scored.product.id) {
+                        results.push(scored);
+                    }
+                }
+            }
+        }
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.overrides_for(query).apply(&mut results, |r| r.product.id);
+        results
+    }
+
+    fn rank_term(&self, query: &str) -> Vec<ScoredProduct<'_>> {
         let mut results = Vec::new();
 
         let name_matches = self.index.search_by_name(query);
         for id in name_matches {
-            if let Some(product) = self.index.get_product(id) {
-                let score = product.search_score(query);
-                let match_type = if product.name.to_lowercase() == query.to_lowercase() {
+            if let Some(product) = self.searchable_product(id) {
+                let score = self.adjust_score(product, self.query_score(product, query, &SearchFields::all()));
+                let match_type = if normalize_term(&product.name) == normalize_term(query) {
                     MatchType::ExactName
                 } else {
                     MatchType::PartialName
                 };
 
-                results.push(SearchResult {
-                    product: product.clone(),
-                    score,
-                    match_type,
-                });
+                results.push(ScoredProduct {
+                    product,
+                    score,
+                    match_type,
+                    components: self.score_components(product, query, &SearchFields::all()),
+                });
+            }
+        }
+
+        let name_ids: HashSet<u64> = results.iter().map(|r| r.product.id).collect();
+        let category_matches = self.index.search_by_category_text(query);
+        for id in category_matches.into_iter().filter(|id| !name_ids.contains(id)) {
+            if let Some(product) = self.searchable_product(id) {
+                results.push(ScoredProduct {
+                    product,
+                    score: self.adjust_score(product, self.query_score(product, query, &SearchFields::all())),
+                    match_type: MatchType::Category,
+                    components: self.score_components(product, query, &SearchFields::all()),
+                });
+            }
+        }
+
+        results
+    }
+
+    /// Jaccard overlap of the two queries' result id sets, in [0, 1]. Two
+    /// queries that both return nothing overlap 0.0.
+    pub fn result_overlap(&self, query_a: &str, query_b: &str) -> f64 {
+        let ids = |query: &str| -> HashSet<u64> { self.rank_basic(query).iter().map(|r| r.product.id).collect() };
+        let (a, b) = (ids(query_a), ids(query_b));
+
+        let union = a.union(&b).count();
+        if union == 0 {
+            return 0.0;
+        }
+
+        a.intersection(&b).count() as f64 / union as f64
+    }
+
+    pub fn fuzzy_search(&self, query: &str, max_distance: usize) -> Vec<SearchResult> {
+        let started = Instant::now();
+        self.record_query(query);
+
+        let mut results: Vec<SearchResult> = self
+            .index
+            .search_by_name_fuzzy(query, max_distance)
+            .into_iter()
+            .filter_map(|(id, distance)| {
+                self.searchable_product(id).map(|product| SearchResult {
+                    product: product.clone(),
+                    score: self.adjust_score(product, product.fuzzy_search_score(query, distance)),
+                    match_type: MatchType::PartialName,
+                    // Fuzzy scores add a name bonus and don't weight brands.
+                    score_components: self.score_components(product, query, &SearchFields::all()).map(|components| {
+                        ScoreBreakdown {
+                            relevance: product.fuzzy_relevance(query, distance),
+                            brand_weight: 1.0,
+                            ..components
+                        }
+                    }),
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.overrides_for(query).apply(&mut results, |r| r.product.id);
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    pub fn search_corrected(&self, query: &str) -> (Vec<SearchResult>, Option<String>) {
+        let started = Instant::now();
+        self.record_query(query);
+
+        let results = self.run_basic_search(query);
+        if results.len() >= MIN_RESULTS_BEFORE_CORRECTION {
+            self.notify_search(query, results.len(), started);
+            return (results, None);
+        }
+
+        let mut changed = false;
+        let corrected: Vec<String> = query
+            .split_whitespace()
+            .map(|word| match self.index.correct_term(word) {
+                Some(correction) => {
+                    changed = true;
+                    correction
+                }
+                None => word.to_lowercase(),
+            })
+            .collect();
+
+        if !changed {
+            self.notify_search(query, results.len(), started);
+            return (results, None);
+        }
+
+        let corrected_query = corrected.join(" ");
+        let corrected_results = self.run_basic_search(&corrected_query);
+        self.notify_search(query, corrected_results.len(), started);
+        (corrected_results, Some(corrected_query))
+    }
+
+    pub fn search_fields(&self, query: &str, fields: SearchFields) -> Vec<SearchResult> {
+        let started = Instant::now();
+        self.record_query(query);
+        let mut candidates = HashSet::new();
+
+        if fields.name {
+            candidates.extend(self.index.search_by_name(query));
+        }
+
+        if fields.brand {
+            candidates.extend(self.index.search_by_brand(query));
+        }
+
+        if fields.tags {
+            for word in query.split_whitespace() {
+                candidates.extend(self.index.search_by_tag_prefix(word));
+            }
+        }
+
+        if fields.category {
+            candidates.extend(self.index.search_by_category_text(query));
+        }
+
+        if fields.description {
+            let query_lower = normalize_term(query);
+            candidates.extend(
+                self.index
+                    .all_products()
+                    .iter()
+                    .filter(|product| normalize_term(&product.description).contains(&query_lower))
+                    .map(|product| product.id),
+            );
+        }
+
+        let mut results: Vec<SearchResult> = candidates
+            .into_iter()
+            .filter_map(|id| self.searchable_product(id))
+            .map(|product| SearchResult {
+                product: product.clone(),
+                score: self.adjust_score(product, self.query_score(product, query, &fields)),
+                match_type: MatchType::Combined,
+                score_components: None,
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.overrides_for(query).apply(&mut results, |r| r.product.id);
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    pub fn basic_search_summaries(&self, query: &str) -> Vec<ProductSummary> {
+        let started = Instant::now();
+        let summaries: Vec<ProductSummary> = self
+            .rank_basic(query)
+            .into_iter()
+            .map(|scored| scored.product.summary())
+            .collect();
+        self.notify_search(query, summaries.len(), started);
+        summaries
+    }
+
+    pub fn search_related_to(&self, query: &str, seed_product_id: u64, max_hops: usize) -> Vec<SearchResult> {
+        let started = Instant::now();
+        let related = self.graph.products_within_hops(seed_product_id, max_hops);
+
+        let mut results = self.run_basic_search(query);
+        results.retain(|result| related.contains_key(&result.product.id));
+
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    pub fn search_dedup(&self, query: &str) -> Vec<SearchResult> {
+        let started = Instant::now();
+        // Already ranked (and overridden); the dedup below is stable, so the
+        // best-scoring listing of each name and brand is the one kept.
+        let mut results = self.run_basic_search(query);
+
+        let mut seen = HashSet::new();
+        results.retain(|result| {
+            let name = result.product.name.split_whitespace().collect::<Vec<_>>().join(" ");
+            let brand = result.product.brand.split_whitespace().collect::<Vec<_>>().join(" ");
+            seen.insert((name.to_lowercase(), brand.to_lowercase()))
+        });
+
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    /// Alternates two rankings of the query's matches: positions 1, 3, 5...
+    /// take the next best by relevance, positions 2, 4, 6... the next best by
+    /// popularity (rating, then review count). Each product appears once;
+    /// popularity picks carry their rating as the score.
+    pub fn search_interleaved(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let started = Instant::now();
+        self.record_query(query);
+
+        let relevance = self.run_basic_search(query);
+        let mut popularity: Vec<SearchResult> = relevance
+            .iter()
+            .map(|result| SearchResult {
+                product: result.product.clone(),
+                score: result.product.rating as f64,
+                match_type: result.match_type.clone(),
+                score_components: None,
+            })
+            .collect();
+        popularity.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap()
+                .then_with(|| b.product.review_count.cmp(&a.product.review_count))
+                .then_with(|| a.product.id.cmp(&b.product.id))
+        });
+
+        let mut sources = [relevance.into_iter(), popularity.into_iter()];
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        let mut turn = 0;
+
+        while results.len() < limit {
+            match sources[turn % 2].find(|result| !seen.contains(&result.product.id)) {
+                Some(result) => {
+                    seen.insert(result.product.id);
+                    results.push(result);
+                }
+                None => break,
             }
+            turn += 1;
         }
 
-        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.notify_search(query, results.len(), started);
+        results
+    }
+
+    /// A reproducible random sample of `n` matches, drawn without replacement
+    /// with probability proportional to score (Efraimidis-Spirakis keys).
+    /// The same `seed` over the same catalog always yields the same sample,
+    /// in draw order. Zero-scored matches are only drawn once nothing else is
+    /// left.
+    pub fn search_sample(&self, query: &str, n: usize, seed: u64) -> Vec<SearchResult> {
+        let started = Instant::now();
+        self.record_query(query);
+
+        let mut matches = self.run_basic_search(query);
+        matches.sort_by_key(|result| result.product.id);
+
+        let mut state = seed;
+        let mut keyed: Vec<(f64, SearchResult)> = matches
+            .into_iter()
+            .map(|result| {
+                let u = next_unit(&mut state);
+                let key = if result.score > 0.0 { u.powf(1.0 / result.score) } else { 0.0 };
+                (key, result)
+            })
+            .collect();
+
+        keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        let results: Vec<SearchResult> = keyed.into_iter().take(n).map(|(_, result)| result).collect();
+
+        self.notify_search(query, results.len(), started);
         results
     }
 
     pub fn search_by_category(&self, category: &Category) -> Vec<SearchResult> {
+        let started = Instant::now();
         let category_matches = self.index.search_by_category(category);
         let mut results = Vec::new();
 
         for id in category_matches {
-            if let Some(product) = self.index.get_product(id) {
+            if let Some(product) = self.visible_product(id) {
                 results.push(SearchResult {
                     product: product.clone(),
                     score: 1.0 + product.rating as f64 / 10.0,
                     match_type: MatchType::Category,
+                    score_components: None,
                 });
             }
         }
 
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.notify_search(&category.to_string(), results.len(), started);
         results
     }
 
     pub fn search_by_brand(&self, brand: &str) -> Vec<SearchResult> {
+        let started = Instant::now();
         let brand_matches = self.index.search_by_brand(brand);
         let mut results = Vec::new();
 
         for id in brand_matches {
-            if let Some(product) = self.index.get_product(id) {
+            if let Some(product) = self.searchable_product(id) {
+                if self.case_sensitive_brand && !brand_equals(&product.brand, brand, true) {
+                    continue;
+                }
+
                 results.push(SearchResult {
                     product: product.clone(),
                     score: 1.0 + product.rating as f64 / 10.0,
                     match_type: MatchType::Brand,
+                    score_components: None,
                 });
             }
         }
 
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.notify_search(brand, results.len(), started);
+        results
+    }
+
+    pub fn deals(&self, limit: usize) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = self
+            .index
+            .all_products()
+            .into_iter()
+            .filter(|product| product.active && self.in_default_category(product))
+            .filter_map(|product| {
+                product.discount_percent().map(|discount| SearchResult {
+                    product: product.clone(),
+                    score: discount,
+                    match_type: MatchType::Combined,
+                    score_components: None,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score.partial_cmp(&a.score)
+                .unwrap()
+                .then_with(|| a.product.id.cmp(&b.product.id))
+        });
+        results.truncate(limit);
         results
     }
 
+    pub fn export_catalog_json<W: Write>(&self, w: W) -> io::Result<()> {
+        self.index.export_json(w)
+    }
+
+    /// All-or-nothing catalog import with the same checks as
+    /// `ProductIndex::import_json`.
+    pub fn import_catalog_json<R: Read>(&mut self, r: R) -> Result<usize, MegastoreError> {
+        let products: Vec<Product> = serde_json::from_reader(r)?;
+        self.index.check_new_products(&products)?;
+        let count = products.len();
+        for product in products {
+            self.add_product(product);
+        }
+        Ok(count)
+    }
+
+    pub fn audit_relations(&self) -> Vec<RelationIssue> {
+        let mut issues = Vec::new();
+
+        for (product_id_1, product_id_2, _, relation_type) in self.graph.all_edges() {
+            let (first, second) = match (self.index.get_product(product_id_1), self.index.get_product(product_id_2)) {
+                (Some(first), Some(second)) => (first, second),
+                (first, second) => {
+                    if first.is_none() {
+                        issues.push(RelationIssue::MissingProduct { product_id: product_id_1 });
+                    }
+                    if second.is_none() {
+                        issues.push(RelationIssue::MissingProduct { product_id: product_id_2 });
+                    }
+                    continue;
+                }
+            };
+
+            let issue = match relation_type {
+                RelationType::Similar if first.category != second.category => {
+                    Some(RelationIssue::SimilarAcrossCategories { product_id_1, product_id_2 })
+                }
+                RelationType::SameBrand if normalize_term(&first.brand) != normalize_term(&second.brand) => {
+                    Some(RelationIssue::BrandMismatch { product_id_1, product_id_2 })
+                }
+                RelationType::SameCategory if first.category != second.category => {
+                    Some(RelationIssue::CategoryMismatch { product_id_1, product_id_2 })
+                }
+                _ => None,
+            };
+            issues.extend(issue);
+        }
+
+        issues
+    }
+
+    pub fn index(&self) -> &ProductIndex {
+        &self.index
+    }
+
+    pub fn graph(&self) -> &RecommendationGraph {
+        &self.graph
+    }
+
     pub fn get_product_count(&self) -> usize {
         self.index.product_count()
     }
@@ -220,21 +1304,122 @@ impl SearchEngine {
         (self.graph.product_count(), self.graph.edge_count())
     }
 
+    /// Hash of the catalog and relation graph, stable across builds like
+    /// `ProductIndex::content_hash`.
+    pub fn state_hash(&self) -> u64 {
+        // Edges are summed rather than XORed so identical parallel edges
+        // don't cancel each other out.
+        let edges_hash = self
+            .graph
+            .all_edges()
+            .into_iter()
+            .fold(0u64, |hash, (a, b, weight, relation_type)| {
+                let mut hasher = Fnv1a::new();
+                hasher.write_u64(a.min(b));
+                hasher.write_u64(a.max(b));
+                hasher.write_u64(u64::from(weight.to_bits()));
+                match relation_type {
+                    RelationType::Custom(ref name) => {
+                        hasher.write_u8(1);
+                        hasher.write_str(name);
+                    }
+                    ref relation_type => {
+                        hasher.write_u8(0);
+                        hasher.write_str(&relation_type.to_string());
+                    }
+                }
+                hash.wrapping_add(hasher.finish())
+            });
+
+        let mut hasher = Fnv1a::new();
+        hasher.write_u64(self.index.content_hash());
+        hasher.write_u64(edges_hash);
+        hasher.finish()
+    }
+
     pub fn get_product(&self, id: u64) -> Option<&Product> {
         self.index.get_product(id)
     }
 
+    fn query_candidates(&self, query: &str) -> HashSet<u64> {
+        let mut candidates: HashSet<u64> = self.index.search_by_name(query).into_iter().collect();
+
+        let tag_results = query.split_whitespace()
+            .flat_map(|word| self.index.search_by_tag_prefix(word))
+            .collect::<Vec<_>>();
+        candidates.extend(tag_results);
+        candidates.extend(self.index.search_by_category_text(query));
+
+        candidates
+    }
+
+    pub fn infer_categories(&self, query: &str) -> Vec<(Category, f64)> {
+        let candidates = self.query_candidates(query);
+        let mut counts: HashMap<Category, usize> = HashMap::new();
+
+        for id in &candidates {
+            if let Some(product) = self.visible_product(*id) {
+                *counts.entry(product.category.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let total: usize = counts.values().sum();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        let mut categories: Vec<(Category, f64)> = counts
+            .into_iter()
+            .map(|(category, count)| (category, count as f64 / total as f64))
+            .collect();
+
+        categories.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap()
+                .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+        });
+        categories
+    }
+
+    pub fn query(&self) -> SearchQueryBuilder<'_> {
+        SearchQueryBuilder::new(self)
+    }
+
     pub fn search_with_filters(&self, query: Option<&str>, filters: &SearchFilters) -> Vec<SearchResult> {
+        let started = Instant::now();
+        if let Some(query_str) = query {
+            self.record_query(query_str);
+        }
+        let results = self.run_filtered_search(query, filters);
+        self.notify_search(query.unwrap_or(""), results.len(), started);
+        results
+    }
+
+    fn run_filtered_search(&self, query: Option<&str>, filters: &SearchFilters) -> Vec<SearchResult> {
+        self.rank_filtered(query, filters)
+            .into_iter()
+            .map(ScoredProduct::into_result)
+            .collect()
+    }
+
+    /// `filters` with the engine's default category applied when the caller
+    /// set no category of their own.
+    fn effective_filters<'a>(&self, filters: &'a SearchFilters) -> Cow<'a, SearchFilters> {
+        match self.default_category {
+            Some(ref category) if filters.category.is_none() && filters.categories.is_empty() => {
+                Cow::Owned(filters.clone().category(category.clone()))
+            }
+            _ => Cow::Borrowed(filters),
+        }
+    }
+
+    fn rank_filtered(&self, query: Option<&str>, filters: &SearchFilters) -> Vec<ScoredProduct<'_>> {
+        let filters = &*self.effective_filters(filters);
+
         let mut candidates = HashSet::new();
 
         if let Some(query_str) = query {
-            let name_matches = self.index.search_by_name(query_str);
-            candidates.extend(name_matches);
-
-            let tag_results = query_str.split_whitespace()
-                .flat_map(|word| self.index.search_by_tag(word))
-                .collect::<Vec<_>>();
-            candidates.extend(tag_results);
+            candidates.extend(self.query_candidates(query_str));
         } else {
             candidates.extend(self.index.all_products().iter().map(|p| p.id));
         }
@@ -248,6 +1433,11 @@ impl SearchEngine {
             }
         }
 
+        if !filters.categories.is_empty() {
+            let category_matches: HashSet<u64> = self.index.search_by_categories(&filters.categories).into_iter().collect();
+            candidates.retain(|id| category_matches.contains(id));
+        }
+
         if let Some(ref brand) = filters.brand {
             let brand_matches = self.index.search_by_brand(brand);
             if query.is_some() || filters.category.is_some() {
@@ -257,29 +1447,70 @@ impl SearchEngine {
             }
         }
 
+        if !filters.exclude_ids.is_empty() {
+            candidates.retain(|id| !filters.exclude_ids.contains(id));
+        }
+
+        if let Some(ref region) = filters.region {
+            let restricted = self.index.restricted_in(region);
+            candidates.retain(|id| !restricted.contains(id));
+        }
+
         let mut results = Vec::new();
         for id in candidates {
             if let Some(product) = self.index.get_product(id) {
                 if filters.matches(product) {
                     let score = if let Some(query_str) = query {
-                        product.search_score(query_str)
+                        self.query_score(product, query_str, &SearchFields::all())
                     } else {
                         product.rating as f64
                     };
 
-                    results.push(SearchResult {
-                        product: product.clone(),
-                        score,
+                    results.push(ScoredProduct {
+                        product,
+                        score: self.adjust_score(product, score),
                         match_type: MatchType::Combined,
+                        components: query.and_then(|query_str| self.score_components(product, query_str, &SearchFields::all())),
                     });
                 }
             }
         }
 
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        if let Some(query_str) = query {
+            self.overrides_for(query_str).apply(&mut results, |r| r.product.id);
+        }
         results
     }
 
+    /// Filtered search returning each parent product once, alongside the
+    /// variants that satisfy the filters' variant attributes and stock rule.
+    pub fn search_with_variants(&self, query: Option<&str>, filters: &SearchFilters) -> Vec<(SearchResult, Vec<Variant>)> {
+        self.search_with_filters(query, filters)
+            .into_iter()
+            .map(|result| {
+                let variants = result
+                    .product
+                    .matching_variants(&filters.variant_attributes, filters.in_stock_only)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                (result, variants)
+            })
+            .collect()
+    }
+
+    pub fn search_with_filters_summaries(&self, query: Option<&str>, filters: &SearchFilters) -> Vec<ProductSummary> {
+        let started = Instant::now();
+        let summaries: Vec<ProductSummary> = self
+            .rank_filtered(query, filters)
+            .into_iter()
+            .map(|scored| scored.product.summary())
+            .collect();
+        self.notify_search(query.unwrap_or(""), summaries.len(), started);
+        summaries
+    }
+
     pub fn search_by_price_range(&self, min_price: f64, max_price: f64) -> Vec<SearchResult> {
         let filters = SearchFilters::new().price_range(min_price, max_price);
         self.search_with_filters(None, &filters)
@@ -309,25 +1540,224 @@ impl SearchEngine {
     }
 
     pub fn get_recommendations_for_product(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
-        let recommendations = self.graph.get_recommendations(product_id, limit);
-        let mut results = Vec::new();
+        self.visible_recommendations(self.graph.get_recommendations(product_id, usize::MAX), limit)
+    }
 
-        for (rec_id, score) in recommendations {
-            if let Some(product) = self.index.get_product(rec_id) {
-                results.push(SearchResult {
-                    product: product.clone(),
-                    score: score as f64,
-                    match_type: MatchType::Recommendation,
-                });
+    pub fn get_recommendations_scored(&self, product_id: u64, limit: usize) -> Vec<(SearchResult, u8)> {
+        let results = self.get_recommendations_for_product(product_id, limit);
+
+        let max = results.iter().map(|r| r.score).fold(f64::NEG_INFINITY, f64::max);
+        let min = results.iter().map(|r| r.score).fold(f64::INFINITY, f64::min);
+        let range = max - min;
+
+        results
+            .into_iter()
+            .map(|result| {
+                let percent = if range > 0.0 {
+                    ((result.score - min) / range * 100.0).round() as u8
+                } else {
+                    100
+                };
+                (result, percent)
+            })
+            .collect()
+    }
+
+    /// Reranks graph recommendations by the recommended product's own
+    /// rating: each score is multiplied by `0.5 + rating / 10`, so a 5-star
+    /// product keeps its full graph score and an unrated one keeps half.
+    pub fn get_recommendations_quality_weighted(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
+        let mut results = self.get_recommendations_for_product(product_id, usize::MAX);
+        for result in &mut results {
+            result.score *= 0.5 + result.product.rating as f64 / 10.0;
+        }
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(limit);
+        results
+    }
+
+    pub fn try_get_recommendations_for_product(&self, product_id: u64, limit: usize) -> Result<Vec<SearchResult>, MegastoreError> {
+        if self.index.get_product(product_id).is_none() {
+            return Err(MegastoreError::UnknownProduct(product_id));
+        }
+
+        Ok(self.get_recommendations_for_product(product_id, limit))
+    }
+
+    pub fn get_recommendations_excluding(&self, product_id: u64, exclude: &HashSet<u64>, limit: usize) -> Vec<SearchResult> {
+        let recommendations = self
+            .graph
+            .get_recommendations(product_id, usize::MAX)
+            .into_iter()
+            .filter(|(rec_id, _)| !exclude.contains(rec_id))
+            .collect();
+
+        self.visible_recommendations(recommendations, limit)
+    }
+
+    /// Multi-seed recommendations for a cart, reranked so one category
+    /// can't fill the strip: every pick from a category already shown has
+    /// its score halved again before competing for the next slot.
+    pub fn cart_recommendations(&self, product_ids: &[u64], limit: usize) -> Vec<SearchResult> {
+        let mut candidates = self.visible_recommendations(
+            self.graph.get_recommendations_multi(product_ids, usize::MAX),
+            usize::MAX,
+        );
+
+        let mut picked: Vec<SearchResult> = Vec::new();
+        let mut category_counts: HashMap<Category, i32> = HashMap::new();
+
+        while picked.len() < limit && !candidates.is_empty() {
+            let adjusted = |result: &SearchResult| {
+                let repeats = category_counts.get(&result.product.category).copied().unwrap_or(0);
+                result.score * CATEGORY_REPEAT_PENALTY.powi(repeats)
+            };
+
+            let best = (0..candidates.len())
+                .max_by(|&a, &b| {
+                    adjusted(&candidates[a])
+                        .partial_cmp(&adjusted(&candidates[b]))
+                        .unwrap()
+                        .then_with(|| candidates[b].product.id.cmp(&candidates[a].product.id))
+                })
+                .unwrap();
+
+            let result = candidates.swap_remove(best);
+            *category_counts.entry(result.product.category.clone()).or_insert(0) += 1;
+            picked.push(result);
+        }
+
+        picked
+    }
+
+    /// Intra-list diversity: the average dissimilarity over all pairs of
+    /// results, in [0, 1]. A pair's similarity is the mean of category
+    /// equality (0 or 1) and the Jaccard overlap of their normalized tags;
+    /// untagged pairs share no tags. Lists shorter than two score 0.0.
+    pub fn list_diversity(&self, results: &[SearchResult]) -> f64 {
+        let tag_sets: Vec<HashSet<String>> = results
+            .iter()
+            .map(|result| result.product.tags.iter().map(|tag| normalize_term(tag)).collect())
+            .collect();
+
+        let mut total = 0.0;
+        let mut pairs = 0;
+        for i in 0..results.len() {
+            for j in (i + 1)..results.len() {
+                let same_category = if results[i].product.category == results[j].product.category { 1.0 } else { 0.0 };
+                let union = tag_sets[i].union(&tag_sets[j]).count();
+                let tag_similarity = if union == 0 {
+                    0.0
+                } else {
+                    tag_sets[i].intersection(&tag_sets[j]).count() as f64 / union as f64
+                };
+
+                total += 1.0 - (same_category + tag_similarity) / 2.0;
+                pairs += 1;
             }
         }
 
+        if pairs == 0 { 0.0 } else { total / pairs as f64 }
+    }
+
+    /// The most structurally important products in `category`: PageRank is
+    /// computed over the whole graph, so links from other categories count,
+    /// but only in-category products are returned, scored by their rank.
+    pub fn trending_in_category(&self, category: &Category, limit: usize) -> Vec<SearchResult> {
+        let ranks = self.graph.pagerank(TRENDING_DAMPING, TRENDING_MAX_ITERATIONS);
+
+        let mut results: Vec<SearchResult> = self
+            .index
+            .search_by_category(category)
+            .into_iter()
+            .filter_map(|id| self.visible_product(id))
+            .filter(|product| !self.suppressed.contains(&product.id))
+            .map(|product| SearchResult {
+                product: product.clone(),
+                score: ranks.get(&product.id).copied().unwrap_or(0.0),
+                match_type: MatchType::Recommendation,
+                score_components: None,
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap()
+                .then_with(|| a.product.id.cmp(&b.product.id))
+        });
+        results.truncate(limit);
         results
     }
 
+    pub fn get_second_degree_recommendations(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
+        self.visible_recommendations(self.graph.get_recommendations_depth_2(product_id, usize::MAX), limit)
+    }
+
+    // Inactive products stay in the graph so their edges keep connecting
+    // neighbours; they are only dropped from what gets returned.
+    fn visible_recommendations(&self, recommendations: Vec<(u64, f32)>, limit: usize) -> Vec<SearchResult> {
+        recommendations
+            .into_iter()
+            .filter_map(|(rec_id, score)| {
+                self.recommendable_product(rec_id).map(|product| SearchResult {
+                    product: product.clone(),
+                    score: score as f64,
+                    match_type: MatchType::Recommendation,
+                    score_components: None,
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
+    pub fn constrained_recommendations(&self, product_id: u64, filters: &SearchFilters, limit: usize) -> Vec<SearchResult> {
+        self.graph
+            .get_recommendations(product_id, usize::MAX)
+            .into_iter()
+            .filter_map(|(rec_id, score)| {
+                self.index
+                    .get_product(rec_id)
+                    .filter(|product| !self.suppressed.contains(&product.id))
+                    .filter(|product| filters.matches(product))
+                    .map(|product| SearchResult {
+                        product: product.clone(),
+                        score: score as f64,
+                        match_type: MatchType::Recommendation,
+                        score_components: None,
+                    })
+            })
+            .take(limit)
+            .collect()
+    }
+
+    pub fn recommend_from_filters(&self, filters: &SearchFilters, limit: usize) -> Vec<SearchResult> {
+        let seeds: Vec<u64> = self
+            .rank_filtered(None, filters)
+            .iter()
+            .map(|scored| scored.product.id)
+            .collect();
+
+        self.graph
+            .get_recommendations_multi(&seeds, usize::MAX)
+            .into_iter()
+            .filter_map(|(rec_id, score)| {
+                self.recommendable_product(rec_id).map(|product| SearchResult {
+                    product: product.clone(),
+                    score: score as f64,
+                    match_type: MatchType::Recommendation,
+                    score_components: None,
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
     pub fn search_with_recommendations(&self, query: &str, include_recommendations: bool, limit: usize) -> Vec<SearchResult> {
+        let started = Instant::now();
         let mut all_results = Vec::new();
-        let search_results = self.basic_search(query);
+        let search_results = self.run_basic_search(query);
 
         let mut seen_ids = HashSet::new();
 
@@ -342,12 +1772,13 @@ impl SearchEngine {
 
             for (rec_id, rec_score) in recommendations {
                 if !seen_ids.contains(&rec_id) {
-                    if let Some(product) = self.index.get_product(rec_id) {
+                    if let Some(product) = self.recommendable_product(rec_id) {
                         seen_ids.insert(rec_id);
                         all_results.push(SearchResult {
                             product: product.clone(),
                             score: rec_score as f64 * 0.8,
                             match_type: MatchType::Recommendation,
+                            score_components: None,
                         });
                     }
                 }
@@ -355,50 +1786,315 @@ impl SearchEngine {
         }
 
         all_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        self.overrides_for(query).apply(&mut all_results, |r| r.product.id);
         all_results.truncate(limit);
+        self.notify_search(query, all_results.len(), started);
         all_results
     }
 
     pub fn search_similar_products(&self, product_id: u64) -> Vec<SearchResult> {
+        self.search_similar_products_limited(product_id, usize::MAX)
+    }
+
+    pub fn search_similar_products_limited(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
         let similar_ids = self.graph.get_similar_products(product_id);
         let mut results = Vec::new();
 
         for similar_id in similar_ids {
-            if let Some(product) = self.index.get_product(similar_id) {
+            if let Some(product) = self.recommendable_product(similar_id) {
                 results.push(SearchResult {
                     product: product.clone(),
                     score: product.rating as f64,
                     match_type: MatchType::Recommendation,
+                    score_components: None,
                 });
             }
         }
 
         results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(limit);
         results
     }
 
-    pub fn get_frequently_bought_together(&self, product_id: u64) -> Vec<SearchResult> {
-        let bought_together_ids = self.graph.get_frequently_bought_together(product_id);
+    pub fn nearest_by_tags(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
+        let seed_vector = self.index.tag_vector(product_id);
+        if seed_vector.is_empty() {
+            return Vec::new();
+        }
+
+        let seed_norm = seed_vector.values().map(|w| w * w).sum::<f64>().sqrt();
+
+        let mut candidates = HashSet::new();
+        for tag in seed_vector.keys() {
+            candidates.extend(self.index.search_by_tag(tag));
+        }
+        candidates.remove(&product_id);
+
         let mut results = Vec::new();
+        for id in candidates {
+            if let Some(product) = self.visible_product(id) {
+                let vector = self.index.tag_vector(id);
+                let norm = vector.values().map(|w| w * w).sum::<f64>().sqrt();
+                let dot: f64 = seed_vector
+                    .iter()
+                    .filter_map(|(tag, weight)| vector.get(tag).map(|other| weight * other))
+                    .sum();
 
-        for id in bought_together_ids {
-            if let Some(product) = self.index.get_product(id) {
                 results.push(SearchResult {
                     product: product.clone(),
-                    score: product.rating as f64,
-                    match_type: MatchType::Recommendation,
+                    score: dot / (seed_norm * norm),
+                    match_type: MatchType::Tag,
+                    score_components: None,
                 });
             }
         }
 
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(limit);
+        results
+    }
+
+    /// Same-category neighbors by weighted Euclidean distance over price and
+    /// rating, each min-max normalized across the category. Scored as
+    /// `1 / (1 + distance)`, so an identical product scores 1.0.
+    pub fn nearest_by_attributes(&self, product_id: u64, limit: usize, price_weight: f64, rating_weight: f64) -> Vec<SearchResult> {
+        let seed = match self.index.get_product(product_id) {
+            Some(product) => product,
+            None => return Vec::new(),
+        };
+
+        let peers: Vec<&Product> = self
+            .index
+            .search_by_category(&seed.category)
+            .into_iter()
+            .filter_map(|id| self.index.get_product(id))
+            .collect();
+
+        let span = |values: Vec<f64>| {
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let range = max - min;
+            move |value: f64| if range > 0.0 { (value - min) / range } else { 0.0 }
+        };
+        let price_norm = span(peers.iter().map(|p| p.price).collect());
+        let rating_norm = span(peers.iter().map(|p| p.rating as f64).collect());
+
+        let mut results: Vec<SearchResult> = peers
+            .iter()
+            .filter(|product| product.id != product_id)
+            .filter_map(|product| self.visible_product(product.id))
+            .map(|product| {
+                let price_diff = price_norm(product.price) - price_norm(seed.price);
+                let rating_diff = rating_norm(product.rating as f64) - rating_norm(seed.rating as f64);
+                let distance = (price_weight * price_diff * price_diff + rating_weight * rating_diff * rating_diff).sqrt();
+
+                SearchResult {
+                    product: product.clone(),
+                    score: 1.0 / (1.0 + distance),
+                    match_type: MatchType::Category,
+                    score_components: None,
+                }
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(limit);
+        results
+    }
+
+    pub fn recommend_by_shared_tags(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
+        let seed = match self.index.get_product(product_id) {
+            Some(product) => product,
+            None => return Vec::new(),
+        };
+
+        let total_products = self.index.product_count() as f64;
+        let mut scores: HashMap<u64, f64> = HashMap::new();
+        let seed_tags: HashSet<String> = seed.tags.iter().map(|tag| normalize_term(tag)).collect();
+
+        for tag in &seed_tags {
+            let usage = self.index.tag_usage(tag);
+            if usage == 0 {
+                continue;
+            }
+
+            let rarity = (total_products / usage as f64).ln() + 1.0;
+            for id in self.index.search_by_tag(tag) {
+                if id != product_id {
+                    *scores.entry(id).or_insert(0.0) += rarity;
+                }
+            }
+        }
+
+        let mut results: Vec<SearchResult> = scores
+            .into_iter()
+            .filter_map(|(id, score)| {
+                self.recommendable_product(id).map(|product| SearchResult {
+                    product: product.clone(),
+                    score,
+                    match_type: MatchType::Tag,
+                    score_components: None,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        results.truncate(limit);
+        results
+    }
+
+    pub fn tag_facets(&self, query: &str, limit: usize) -> Vec<(String, usize)> {
+        let query_terms: Vec<String> = query.split_whitespace().map(|word| word.to_lowercase()).collect();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for result in self.run_filtered_search(Some(query), &SearchFilters::new()) {
+            let tags: HashSet<String> = result.product.tags.iter().map(|tag| normalize_term(tag)).collect();
+            for tag in tags {
+                // Tags the query already matched can't narrow the results further
+                if query_terms.iter().any(|term| tag.starts_with(term.as_str())) {
+                    continue;
+                }
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+
+        let mut facets: Vec<(String, usize)> = counts.into_iter().collect();
+        facets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        facets.truncate(limit);
+        facets
+    }
+
+    pub fn related_tags(&self, tag: &str, limit: usize) -> Vec<(String, f64)> {
+        let tag_lower = normalize_term(tag);
+        let tag_usage = self.index.tag_usage(&tag_lower);
+        if tag_usage == 0 {
+            return Vec::new();
+        }
+
+        let mut co_occurrences: HashMap<String, usize> = HashMap::new();
+        for id in self.index.search_by_tag(&tag_lower) {
+            if let Some(product) = self.visible_product(id) {
+                let tags: HashSet<String> = product.tags.iter().map(|t| normalize_term(t)).collect();
+                for other in tags {
+                    if other != tag_lower {
+                        *co_occurrences.entry(other).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let mut related: Vec<(String, f64)> = co_occurrences
+            .into_iter()
+            .map(|(other, count)| {
+                let other_usage = self.index.tag_usage(&other);
+                let score = count as f64 / ((tag_usage * other_usage) as f64).sqrt();
+                (other, score)
+            })
+            .collect();
+
+        related.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then_with(|| a.0.cmp(&b.0)));
+        related.truncate(limit);
+        related
+    }
+
+    /// Pairs are `(from, into)`: the less used tag points at the more used
+    /// one, so passing a pair straight to `merge_tags` keeps the dominant form.
+    pub fn suggest_tag_merges(&self, max_distance: usize) -> Vec<(String, String)> {
+        let tags = self.index.all_tags();
+        let mut merges = Vec::new();
+
+        for (i, a) in tags.iter().enumerate() {
+            for b in &tags[i + 1..] {
+                if edit_distance(a, b) > max_distance {
+                    continue;
+                }
+
+                let usage_a = self.index.tag_usage(a);
+                let usage_b = self.index.tag_usage(b);
+                let keep_a = usage_a > usage_b || (usage_a == usage_b && a.len() <= b.len());
+                if keep_a {
+                    merges.push((b.clone(), a.clone()));
+                } else {
+                    merges.push((a.clone(), b.clone()));
+                }
+            }
+        }
+
+        merges
+    }
+
+    pub fn merge_tags(&mut self, from: &str, into: &str) -> usize {
+        self.index.rename_tag(from, into)
+    }
+
+    pub fn add_tag_to_product(&mut self, id: u64, tag: String) -> bool {
+        self.index.add_tag(id, tag)
+    }
+
+    pub fn remove_tag_from_product(&mut self, id: u64, tag: &str) -> bool {
+        self.index.remove_tag(id, tag)
+    }
+
+    pub fn get_frequently_bought_together(&self, product_id: u64) -> Vec<SearchResult> {
+        self.get_frequently_bought_together_limited(product_id, usize::MAX)
+    }
+
+    /// `get_frequently_bought_together` capped at the first `limit` results.
+    pub fn get_frequently_bought_together_limited(&self, product_id: u64, limit: usize) -> Vec<SearchResult> {
+        self.graph
+            .get_frequently_bought_together(product_id)
+            .into_iter()
+            .filter_map(|id| {
+                self.recommendable_product(id).map(|product| SearchResult {
+                    product: product.clone(),
+                    score: product.rating as f64,
+                    match_type: MatchType::Recommendation,
+                    score_components: None,
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
+    pub fn get_also_viewed(&self, product_id: u64) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = self
+            .graph
+            .get_also_viewed(product_id)
+            .into_iter()
+            .filter_map(|id| self.recommendable_product(id))
+            .map(|product| SearchResult {
+                product: product.clone(),
+                score: product.rating as f64,
+                match_type: MatchType::Recommendation,
+                score_components: None,
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
         results
     }
 
     pub fn hybrid_search(&self, query: Option<&str>, filters: &SearchFilters, use_recommendations: bool) -> Vec<SearchResult> {
+        self.run_hybrid_search(query, filters, use_recommendations, default_hybrid_combine)
+    }
+
+    pub fn hybrid_search_with<F>(&self, query: Option<&str>, filters: &SearchFilters, combine: F) -> Vec<SearchResult>
+    where
+        F: Fn(f64, f32) -> f64,
+    {
+        self.run_hybrid_search(query, filters, true, combine)
+    }
+
+    fn run_hybrid_search<F>(&self, query: Option<&str>, filters: &SearchFilters, use_recommendations: bool, combine: F) -> Vec<SearchResult>
+    where
+        F: Fn(f64, f32) -> f64,
+    {
+        let started = Instant::now();
         let mut all_results = Vec::new();
         let mut seen_ids = HashSet::new();
+        let filters = &*self.effective_filters(filters);
 
-        let filtered_results = self.search_with_filters(query, filters);
+        let filtered_results = self.run_filtered_search(query, filters);
         let top_scores: Vec<(u64, f64)> = filtered_results.iter()
             .take(3)
             .map(|r| (r.product.id, r.score))
@@ -416,12 +2112,13 @@ impl SearchEngine {
                 for (rec_id, rec_score) in recommendations {
                     if !seen_ids.contains(&rec_id) {
                         if let Some(product) = self.index.get_product(rec_id) {
-                            if filters.matches(product) {
+                            if filters.matches(product) && !self.suppressed.contains(&rec_id) {
                                 seen_ids.insert(rec_id);
                                 all_results.push(SearchResult {
                                     product: product.clone(),
-                                    score: base_score * 0.5 + rec_score as f64 * 0.5,
+                                    score: combine(base_score, rec_score),
                                     match_type: MatchType::Recommendation,
+                                    score_components: None,
                                 });
                             }
                         }
@@ -431,6 +2128,10 @@ impl SearchEngine {
         }
 
         all_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        if let Some(query_str) = query {
+            self.overrides_for(query_str).apply(&mut all_results, |r| r.product.id);
+        }
+        self.notify_search(query.unwrap_or(""), all_results.len(), started);
         all_results
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,9 @@
+use std::time::Duration;
+
+pub trait SearchObserver: Send + Sync {
+    fn on_search(&self, _query: &str, _result_count: usize, _elapsed: Duration) {}
+}
+
+pub struct NoopObserver;
+
+impl SearchObserver for NoopObserver {}
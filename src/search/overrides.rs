@@ -0,0 +1,43 @@
+/// Merchandising overrides for a query: pinned products jump to the top of
+/// the matching results, in the order listed, and buried products are dropped.
+/// Pinning never adds a product that didn't match the query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOverrides {
+    pub pinned: Vec<u64>,
+    pub buried: Vec<u64>,
+}
+
+impl SearchOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pin(mut self, product_id: u64) -> Self {
+        self.pinned.push(product_id);
+        self
+    }
+
+    pub fn bury(mut self, product_id: u64) -> Self {
+        self.buried.push(product_id);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pinned.is_empty() && self.buried.is_empty()
+    }
+
+    /// Reorders `items` in place; `id_of` extracts the product id of an item.
+    pub(crate) fn apply<T>(&self, items: &mut Vec<T>, id_of: impl Fn(&T) -> u64) {
+        if self.is_empty() {
+            return;
+        }
+
+        items.retain(|item| !self.buried.contains(&id_of(item)));
+
+        let rank = |item: &T| {
+            let id = id_of(item);
+            self.pinned.iter().position(|&pinned| pinned == id).unwrap_or(usize::MAX)
+        };
+        items.sort_by_key(rank);
+    }
+}
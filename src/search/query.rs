@@ -0,0 +1,126 @@
+use super::{SearchEngine, SearchFilters, SearchResult};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum SortBy {
+    #[default]
+    Relevance,
+    PriceAsc,
+    PriceDesc,
+    RatingDesc,
+    NameAsc,
+    /// Keys applied lexicographically; ties on every key fall back to
+    /// ascending product id.
+    Compound(Vec<SortKey>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Relevance,
+    PriceAsc,
+    PriceDesc,
+    RatingAsc,
+    RatingDesc,
+    NameAsc,
+}
+
+impl SortKey {
+    fn compare(&self, a: &SearchResult, b: &SearchResult) -> Ordering {
+        match self {
+            SortKey::Relevance => b.score.partial_cmp(&a.score).unwrap(),
+            SortKey::PriceAsc => a.product.price_cents().cmp(&b.product.price_cents()),
+            SortKey::PriceDesc => b.product.price_cents().cmp(&a.product.price_cents()),
+            SortKey::RatingAsc => a.product.rating.partial_cmp(&b.product.rating).unwrap(),
+            SortKey::RatingDesc => b.product.rating.partial_cmp(&a.product.rating).unwrap(),
+            SortKey::NameAsc => a.product.name.to_lowercase().cmp(&b.product.name.to_lowercase()),
+        }
+    }
+}
+
+/// Stable sort, so results that tie keep their relevance order.
+pub fn sort_results(results: &mut [SearchResult], sort: &SortBy) {
+    let key = match sort {
+        SortBy::Relevance => SortKey::Relevance,
+        SortBy::PriceAsc => SortKey::PriceAsc,
+        SortBy::PriceDesc => SortKey::PriceDesc,
+        SortBy::RatingDesc => SortKey::RatingDesc,
+        SortBy::NameAsc => SortKey::NameAsc,
+        SortBy::Compound(keys) => {
+            results.sort_by(|a, b| {
+                keys.iter()
+                    .fold(Ordering::Equal, |ordering, key| ordering.then_with(|| key.compare(a, b)))
+                    .then_with(|| a.product.id.cmp(&b.product.id))
+            });
+            return;
+        }
+    };
+
+    results.sort_by(|a, b| key.compare(a, b));
+}
+
+#[derive(Debug)]
+pub struct SearchPage {
+    pub results: Vec<SearchResult>,
+    /// Number of matches before pagination was applied.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+pub struct SearchQueryBuilder<'a> {
+    engine: &'a SearchEngine,
+    text: Option<String>,
+    filters: SearchFilters,
+    sort: SortBy,
+    offset: usize,
+    limit: usize,
+}
+
+impl<'a> SearchQueryBuilder<'a> {
+    pub(crate) fn new(engine: &'a SearchEngine) -> Self {
+        SearchQueryBuilder {
+            engine,
+            text: None,
+            filters: SearchFilters::new(),
+            sort: SortBy::Relevance,
+            offset: 0,
+            limit: usize::MAX,
+        }
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    pub fn filter(mut self, filters: SearchFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn sort(mut self, sort: SortBy) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn page(mut self, offset: usize, limit: usize) -> Self {
+        self.offset = offset;
+        self.limit = limit;
+        self
+    }
+
+    pub fn execute(self) -> SearchPage {
+        let mut results = self.engine.search_with_filters(self.text.as_deref(), &self.filters);
+        sort_results(&mut results, &self.sort);
+
+        let total = results.len();
+        let results = results.into_iter().skip(self.offset).take(self.limit).collect();
+
+        SearchPage {
+            results,
+            total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
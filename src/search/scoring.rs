@@ -0,0 +1,88 @@
+use super::SearchResult;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringConfig {
+    /// Multiplier applied to the score of products with no stock.
+    /// `1.0` leaves them untouched; lower values demote them.
+    pub out_of_stock_factor: f64,
+    /// Scale brand matches by `ln(total / brand_size) + 1`, so a match on a
+    /// boutique brand counts for more than one shared by half the catalog.
+    pub brand_idf: bool,
+}
+
+impl ScoringConfig {
+    pub fn new() -> Self {
+        ScoringConfig {
+            out_of_stock_factor: 1.0,
+            brand_idf: false,
+        }
+    }
+
+    pub fn out_of_stock_factor(mut self, factor: f64) -> Self {
+        self.out_of_stock_factor = factor;
+        self
+    }
+
+    pub fn brand_idf(mut self, enabled: bool) -> Self {
+        self.brand_idf = enabled;
+        self
+    }
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The factors behind a text search score, for comparing ranking changes.
+/// `total()` reproduces the result's `score`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBreakdown {
+    /// Field-match score before any multiplier.
+    pub relevance: f64,
+    /// `1 + rating / 10`.
+    pub rating_multiplier: f64,
+    /// Factor applied to the brand match; above 1.0 only with `brand_idf`.
+    pub brand_weight: f64,
+    /// `out_of_stock_factor` for products with no stock, otherwise 1.0.
+    pub stock_factor: f64,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> f64 {
+        self.relevance * self.rating_multiplier * self.stock_factor
+    }
+}
+
+/// Raw scores are only comparable within the method that produced them:
+/// text matches land in the tens, category and brand listings around 1, and
+/// recommendations follow edge weights. To merge result lists, capture a
+/// `ScoreScale` per list and compare `SearchResult::normalized_score`, which
+/// maps each list onto [0, 1] with its best result at 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreScale {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ScoreScale {
+    pub fn new(min: f64, max: f64) -> Self {
+        ScoreScale { min, max }
+    }
+
+    pub fn from_results(results: &[SearchResult]) -> Self {
+        let min = results.iter().map(|r| r.score).fold(f64::INFINITY, f64::min);
+        let max = results.iter().map(|r| r.score).fold(f64::NEG_INFINITY, f64::max);
+        ScoreScale { min, max }
+    }
+
+    pub fn normalize(&self, score: f64) -> f64 {
+        let range = self.max - self.min;
+        if range > 0.0 {
+            ((score - self.min) / range).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+}
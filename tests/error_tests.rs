@@ -0,0 +1,122 @@
+use megastore_search::{Category, MegastoreError, Product, ProductIndex, RecommendationGraph, SearchEngine};
+use megastore_search::graph::{RelationMergePolicy, RelationType};
+
+fn create_test_product(id: u64, name: &str) -> Product {
+    let mut product = Product::new(
+        id,
+        name.to_string(),
+        "Description".to_string(),
+        "Acme".to_string(),
+        Category::Electronics,
+        100.0,
+    );
+    product.rating = 4.0;
+    product.stock = 10;
+    product
+}
+
+#[test]
+fn test_unknown_product() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop"));
+
+    assert_eq!(
+        engine.try_get_recommendations_for_product(7, 5).unwrap_err(),
+        MegastoreError::UnknownProduct(7)
+    );
+    assert_eq!(
+        engine.add_product_relation(1, 8, 0.5, RelationType::Similar),
+        Err(MegastoreError::UnknownProduct(8))
+    );
+}
+
+#[test]
+fn test_duplicate_id() {
+    let mut engine = SearchEngine::new();
+    assert!(engine.try_add_product(create_test_product(1, "Laptop")).is_ok());
+    assert_eq!(
+        engine.try_add_product(create_test_product(1, "Tablet")),
+        Err(MegastoreError::DuplicateId(1))
+    );
+
+    let mut index = ProductIndex::new();
+    let batch = serde_json::to_vec(&vec![create_test_product(2, "Mouse"), create_test_product(2, "Mouse")]).unwrap();
+    assert_eq!(index.import_json(batch.as_slice()), Err(MegastoreError::DuplicateId(2)));
+    assert_eq!(index.product_count(), 0);
+}
+
+#[test]
+fn test_invalid_product() {
+    let mut engine = SearchEngine::new();
+
+    let mut negative = create_test_product(1, "Laptop");
+    negative.price = -5.0;
+    assert!(matches!(
+        engine.try_add_product(negative),
+        Err(MegastoreError::InvalidProduct { id: 1, .. })
+    ));
+
+    let mut overrated = create_test_product(2, "Tablet");
+    overrated.rating = 6.0;
+    assert!(matches!(overrated.validate(), Err(MegastoreError::InvalidProduct { id: 2, .. })));
+
+    assert!(matches!(
+        create_test_product(3, "  ").validate(),
+        Err(MegastoreError::InvalidProduct { id: 3, .. })
+    ));
+    assert!(create_test_product(4, "Phone").validate().is_ok());
+    assert_eq!(engine.get_product_count(), 0);
+}
+
+#[test]
+fn test_import_error() {
+    let mut engine = SearchEngine::new();
+    let error = engine.import_catalog_json(b"[{\"id\": 1".as_slice()).unwrap_err();
+
+    assert!(matches!(error, MegastoreError::ImportError(_)));
+    assert!(error.to_string().starts_with("import failed"));
+    assert_eq!(engine.get_product_count(), 0);
+}
+
+#[test]
+fn test_graph_error() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop"));
+    engine.add_product(create_test_product(2, "Mouse"));
+
+    assert!(matches!(
+        engine.add_product_relation(1, 1, 0.5, RelationType::Similar),
+        Err(MegastoreError::GraphError(_))
+    ));
+    assert!(matches!(
+        engine.add_product_relation(1, 2, f32::NAN, RelationType::Similar),
+        Err(MegastoreError::GraphError(_))
+    ));
+    assert!(engine.add_product_relation(1, 2, 0.5, RelationType::Similar).is_ok());
+    assert_eq!(engine.get_graph_stats().1, 1);
+}
+
+#[test]
+fn test_import_relations_errors() {
+    let mut graph = RecommendationGraph::new();
+    graph.add_product(1, "Electronics".to_string());
+    graph.add_product(2, "Electronics".to_string());
+    let policy = RelationMergePolicy::KeepStrongest;
+
+    let unknown = [(1, 2, 0.5, RelationType::Similar), (1, 9, 0.5, RelationType::Similar)];
+    assert_eq!(graph.import_relations(&unknown, policy), Err(MegastoreError::UnknownProduct(9)));
+    assert!(matches!(
+        graph.import_relations(&[(2, 2, 0.5, RelationType::Similar)], policy),
+        Err(MegastoreError::GraphError(_))
+    ));
+    assert!(matches!(
+        graph.import_relations(&[(1, 2, f32::NAN, RelationType::Similar)], policy),
+        Err(MegastoreError::GraphError(_))
+    ));
+    assert!(matches!(
+        graph.import_relations(&[(1, 2, -1.0, RelationType::Similar)], policy),
+        Err(MegastoreError::GraphError(_))
+    ));
+    // A rejected batch leaves the graph untouched.
+    assert_eq!(graph.edge_count(), 0);
+}
@@ -1,5 +1,6 @@
 use megastore_search::{RecommendationGraph};
-use megastore_search::graph::RelationType;
+use megastore_search::graph::{RelationMergePolicy, RelationType};
+use std::collections::HashMap;
 
 #[test]
 fn test_add_product_to_graph() {
@@ -180,4 +181,660 @@ fn test_recommendations_depth_2() {
     assert!(product_ids.contains(&2));
     assert!(product_ids.contains(&3)); // Second-degree connection
     assert!(product_ids.contains(&4));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_bridge_products_barbell() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=7 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    // Cluster 1-2-3 and cluster 5-6-7, joined only through product 4
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(1, 3, 0.9);
+    graph.connect_similar_products(2, 3, 0.9);
+    graph.connect_similar_products(5, 6, 0.9);
+    graph.connect_similar_products(5, 7, 0.9);
+    graph.connect_similar_products(6, 7, 0.9);
+    graph.connect_bought_together(3, 4, 0.5);
+    graph.connect_bought_together(4, 5, 0.5);
+
+    let bridges = graph.bridge_products(3);
+    assert_eq!(bridges.len(), 3);
+    assert_eq!(bridges[0].0, 4);
+    assert_eq!(bridges[0].1, 9.0);
+    assert!(bridges[0].1 > bridges[1].1);
+}
+
+#[test]
+fn test_hop_distance_chain() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    // 1 - 2 - 3 - 4, with 5 isolated
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(2, 3, 0.8);
+    graph.connect_bought_together(3, 4, 0.7);
+
+    assert_eq!(graph.hop_distance(1, 1), Some(0));
+    assert_eq!(graph.hop_distance(1, 2), Some(1));
+    assert_eq!(graph.hop_distance(1, 3), Some(2));
+    assert_eq!(graph.hop_distance(1, 4), Some(3));
+    assert_eq!(graph.hop_distance(4, 1), Some(3));
+    assert_eq!(graph.hop_distance(1, 5), None);
+    assert_eq!(graph.hop_distance(1, 999), None);
+}
+
+#[test]
+fn test_get_frequently_bought_together_ranked() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_bought_together(1, 2, 0.4);
+    graph.connect_bought_together(1, 3, 0.9);
+    graph.connect_bought_together(1, 4, 0.1);
+    graph.connect_similar_products(1, 5, 0.95);
+
+    let ranked = graph.get_frequently_bought_together_ranked(1, 0.3, 10);
+    assert_eq!(ranked, vec![(3, 0.9), (2, 0.4)]);
+
+    let top_one = graph.get_frequently_bought_together_ranked(1, 0.0, 1);
+    assert_eq!(top_one, vec![(3, 0.9)]);
+}
+
+#[test]
+fn test_get_top_connections() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=6 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_same_category(1, 2);
+    graph.connect_similar_products(1, 3, 0.9);
+    graph.connect_bought_together(1, 4, 0.3);
+    graph.connect_same_brand(1, 5);
+    graph.connect_bought_together(1, 6, 0.8);
+
+    let top = graph.get_top_connections(1, 3);
+    assert_eq!(top.len(), 3);
+    assert_eq!(top[0], (3, 0.9, RelationType::Similar));
+    assert_eq!(top[1], (6, 0.8, RelationType::BoughtTogether));
+    assert_eq!(top[2], (5, 0.6, RelationType::SameBrand));
+}
+
+fn import_duplicate_pairs(policy: RelationMergePolicy) -> RecommendationGraph {
+    let mut graph = RecommendationGraph::new();
+
+    graph.add_product(1, "Electronics".to_string());
+    graph.add_product(2, "Electronics".to_string());
+
+    let relations = vec![
+        (1, 2, 0.4, RelationType::SameCategory),
+        (2, 1, 0.7, RelationType::Similar),
+        (1, 2, 0.5, RelationType::BoughtTogether),
+    ];
+
+    assert_eq!(graph.import_relations(&relations, policy), Ok(3));
+    assert_eq!(graph.edge_count(), 1);
+    graph
+}
+
+#[test]
+fn test_import_relations_keep_strongest() {
+    let graph = import_duplicate_pairs(RelationMergePolicy::KeepStrongest);
+    assert_eq!(graph.get_connections(1), vec![(2, 0.7, RelationType::Similar)]);
+    assert_eq!(RelationMergePolicy::default(), RelationMergePolicy::KeepStrongest);
+}
+
+#[test]
+fn test_import_relations_keep_latest() {
+    let graph = import_duplicate_pairs(RelationMergePolicy::KeepLatest);
+    assert_eq!(graph.get_connections(1), vec![(2, 0.5, RelationType::BoughtTogether)]);
+}
+
+#[test]
+fn test_import_relations_sum() {
+    let graph = import_duplicate_pairs(RelationMergePolicy::Sum);
+    let connections = graph.get_connections(2);
+    assert_eq!(connections.len(), 1);
+    assert_eq!(connections[0].0, 1);
+    assert!((connections[0].1 - 1.6).abs() < 1e-6);
+    assert_eq!(connections[0].2, RelationType::SameCategory);
+}
+
+#[test]
+fn test_recommendations_normalized_per_type() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=4 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.add_edge(1, 2, 0.9, RelationType::SameCategory);
+    graph.add_edge(1, 3, 0.4, RelationType::BoughtTogether);
+    graph.add_edge(1, 4, 0.2, RelationType::BoughtTogether);
+
+    let raw = graph.get_recommendations(1, 3);
+    assert_eq!(raw[0].0, 2);
+
+    let normalized = graph.get_recommendations_normalized_per_type(1, 3);
+    let ids: Vec<u64> = normalized.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![3, 2, 4]);
+    assert!((normalized[0].1 - 1.5).abs() < 1e-6);
+    assert!((normalized[1].1 - 1.0).abs() < 1e-6);
+    assert!((normalized[2].1 - 0.75).abs() < 1e-6);
+}
+
+#[test]
+fn test_recommendation_coverage() {
+    let mut graph = RecommendationGraph::new();
+    assert_eq!(graph.coverage(), 0.0);
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_bought_together(2, 3, 0.5);
+
+    // Products 4 and 5 are isolated
+    assert_eq!(graph.recommendable_count(), 3);
+    assert!((graph.coverage() - 0.6).abs() < 1e-9);
+
+    graph.connect_same_brand(4, 5);
+    assert_eq!(graph.coverage(), 1.0);
+}
+
+#[test]
+fn test_get_recommendations_multi() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_bought_together(1, 3, 0.4);
+    graph.connect_bought_together(2, 3, 0.4);
+    graph.connect_similar_products(1, 4, 0.5);
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_same_category(2, 5);
+
+    let recommendations = graph.get_recommendations_multi(&[1, 2], 10);
+    let ids: Vec<u64> = recommendations.iter().map(|(id, _)| *id).collect();
+
+    // Seeds are excluded and shared neighbours accumulate score
+    assert_eq!(ids, vec![3, 4, 5]);
+    assert!((recommendations[0].1 - 1.2).abs() < 1e-6);
+}
+
+#[test]
+fn test_set_relation_type() {
+    let mut graph = RecommendationGraph::new();
+
+    graph.add_product(1, "Electronics".to_string());
+    graph.add_product(2, "Electronics".to_string());
+    graph.add_product(3, "Electronics".to_string());
+
+    graph.connect_same_category(1, 2);
+    assert!(graph.get_similar_products(1).is_empty());
+
+    assert!(graph.set_relation_type(2, 1, RelationType::Similar));
+    assert_eq!(graph.get_similar_products(1), vec![2]);
+    assert_eq!(graph.get_connections(1), vec![(2, 0.5, RelationType::Similar)]);
+
+    assert!(!graph.set_relation_type(1, 3, RelationType::Similar));
+    assert!(!graph.set_relation_type(1, 999, RelationType::Similar));
+}
+
+#[test]
+fn test_products_within_hops() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(2, 3, 0.8);
+    graph.connect_similar_products(3, 4, 0.7);
+
+    let within_two = graph.products_within_hops(1, 2);
+    assert_eq!(within_two.len(), 2);
+    assert_eq!(within_two.get(&2), Some(&1));
+    assert_eq!(within_two.get(&3), Some(&2));
+    assert!(!within_two.contains_key(&1));
+
+    assert!(graph.products_within_hops(5, 3).is_empty());
+    assert!(graph.products_within_hops(1, 0).is_empty());
+}
+
+#[test]
+fn test_to_adjacency_json() {
+    let mut graph = RecommendationGraph::new();
+
+    graph.add_product(1, "Electronics".to_string());
+    graph.add_product(2, "Electronics".to_string());
+    graph.add_product(3, "Electronics".to_string());
+
+    graph.connect_bought_together(1, 2, 0.75);
+
+    let json: serde_json::Value = serde_json::from_str(&graph.to_adjacency_json()).unwrap();
+
+    assert_eq!(
+        json["1"],
+        serde_json::json!([{ "id": 2, "weight": 0.75, "type": "BoughtTogether" }])
+    );
+    assert_eq!(json["2"][0]["id"], 1);
+    assert_eq!(json["3"], serde_json::json!([]));
+}
+
+#[test]
+fn test_remove_edges_of_type() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_same_category(1, 2);
+    graph.connect_similar_products(1, 3, 0.9);
+    graph.connect_same_category(2, 3);
+    graph.connect_bought_together(3, 4, 0.7);
+    graph.connect_same_category(4, 5);
+    graph.connect_same_brand(1, 5);
+
+    assert_eq!(graph.remove_edges_of_type(RelationType::SameCategory), 3);
+    assert_eq!(graph.edge_count(), 3);
+    assert_eq!(graph.product_count(), 5);
+
+    assert!(!graph.has_edge(1, 2));
+    assert!(!graph.has_edge(4, 5));
+    assert!(graph.has_edge(1, 3));
+    assert!(graph.has_edge(3, 4));
+    assert!(graph.has_edge(1, 5));
+
+    assert_eq!(graph.remove_edges_of_type(RelationType::SameCategory), 0);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_parallel_precompute_matches_serial() {
+    let mut graph = RecommendationGraph::new();
+    for id in 0..200 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    for id in 0..200u64 {
+        graph.add_edge(id, (id + 1) % 200, 0.9, RelationType::BoughtTogether);
+        graph.add_edge(id, (id * 7 + 3) % 200, 0.4, RelationType::Similar);
+    }
+
+    let serial = graph.precompute_recommendations(5);
+    let parallel = graph.precompute_recommendations_parallel(5);
+
+    assert_eq!(serial.len(), 200);
+    assert_eq!(serial, parallel);
+    assert!(serial.values().all(|recommendations| recommendations.len() <= 5));
+}
+
+#[test]
+fn test_walk_neighbors_prunes_branches() {
+    let mut graph = RecommendationGraph::new();
+    for id in 1..=6 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    graph.connect_bought_together(1, 2, 0.9);
+    graph.connect_bought_together(2, 3, 0.8);
+    graph.connect_similar_products(1, 4, 0.7);
+    graph.connect_bought_together(4, 5, 0.6);
+    graph.connect_bought_together(3, 6, 0.5);
+
+    let mut visited = Vec::new();
+    graph.walk_neighbors(1, 3, |id, _, relation_type| {
+        if *relation_type == RelationType::BoughtTogether {
+            visited.push(id);
+            true
+        } else {
+            false
+        }
+    });
+    assert_eq!(visited, vec![2, 3, 6]);
+
+    let mut shallow = Vec::new();
+    graph.walk_neighbors(1, 1, |id, _, _| {
+        shallow.push(id);
+        true
+    });
+    shallow.sort();
+    assert_eq!(shallow, vec![2, 4]);
+
+    let mut calls = 0;
+    graph.walk_neighbors(99, 3, |_, _, _| {
+        calls += 1;
+        true
+    });
+    assert_eq!(calls, 0);
+}
+
+#[test]
+fn test_relation_counts() {
+    let mut graph = RecommendationGraph::new();
+    for id in 1..=7 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(1, 3, 0.8);
+    graph.connect_similar_products(1, 4, 0.7);
+    graph.connect_bought_together(1, 5, 0.6);
+    graph.connect_bought_together(1, 6, 0.5);
+    graph.connect_same_brand(1, 7);
+    graph.connect_bought_together(2, 3, 0.4);
+
+    let counts = graph.relation_counts(1);
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&RelationType::Similar], 3);
+    assert_eq!(counts[&RelationType::BoughtTogether], 2);
+    assert_eq!(counts[&RelationType::SameBrand], 1);
+    assert!(!counts.contains_key(&RelationType::SameCategory));
+
+    assert!(graph.relation_counts(99).is_empty());
+}
+
+#[test]
+fn test_custom_relation_type() {
+    let mut graph = RecommendationGraph::new();
+    graph.add_product(1, "Electronics".to_string());
+    graph.add_product(2, "Electronics".to_string());
+    graph.add_product(3, "Electronics".to_string());
+
+    let accessory = RelationType::Custom("AccessoryOf".to_string());
+    graph.add_edge(1, 2, 0.8, accessory.clone());
+    graph.add_edge(1, 3, 0.8, RelationType::Custom("ReplacementFor".to_string()));
+
+    let connections = graph.get_connections(1);
+    let (_, weight, relation_type) = connections.iter().find(|(id, _, _)| *id == 2).unwrap();
+    assert_eq!(*weight, 0.8);
+    assert_eq!(*relation_type, accessory);
+    assert_ne!(*relation_type, RelationType::Custom("ReplacementFor".to_string()));
+    assert_eq!(relation_type.to_string(), "AccessoryOf");
+
+    let recommendations = graph.get_recommendations(1, 5);
+    assert!(recommendations.iter().all(|(_, score)| (*score - 0.8).abs() < 1e-6));
+    assert_eq!(graph.relation_counts(1)[&accessory], 1);
+}
+
+#[test]
+fn test_also_viewed_relations() {
+    let mut graph = RecommendationGraph::new();
+    for id in 1..=4 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    graph.connect_also_viewed(1, 2, 0.7);
+    graph.connect_similar_products(1, 3, 0.7);
+    graph.connect_bought_together(1, 4, 0.7);
+
+    let connections = graph.get_connections(1);
+    let also_viewed: Vec<u64> = connections
+        .iter()
+        .filter(|(_, _, relation_type)| *relation_type == RelationType::AlsoViewed)
+        .map(|(id, _, _)| *id)
+        .collect();
+    assert_eq!(also_viewed, vec![2]);
+
+    assert_eq!(graph.get_also_viewed(1), vec![2]);
+    assert_eq!(graph.get_similar_products(1), vec![3]);
+    assert_eq!(graph.get_frequently_bought_together(1), vec![4]);
+
+    let ranked: Vec<u64> = graph.get_recommendations(1, 3).iter().map(|(id, _)| *id).collect();
+    assert_eq!(ranked, vec![4, 3, 2]);
+}
+
+#[test]
+fn test_prune_weak_edges() {
+    let mut graph = RecommendationGraph::new();
+    for id in 1..=4 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(1, 3, 0.2);
+    graph.connect_bought_together(2, 3, 0.1);
+    graph.connect_bought_together(3, 4, 0.5);
+
+    assert_eq!(graph.prune_weak_edges(0.3), 2);
+    assert_eq!(graph.edge_count(), 2);
+    assert!(graph.has_edge(1, 2));
+    assert!(graph.has_edge(3, 4));
+    assert!(!graph.has_edge(1, 3));
+    assert_eq!(graph.prune_weak_edges(0.3), 0);
+}
+
+#[test]
+fn test_prune_to_top_k_per_node() {
+    let mut graph = RecommendationGraph::new();
+    for id in 1..=6 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_similar_products(1, 3, 0.8);
+    graph.connect_similar_products(1, 4, 0.7);
+    graph.connect_similar_products(1, 5, 0.6);
+    graph.connect_similar_products(2, 3, 0.5);
+    graph.connect_similar_products(2, 6, 0.4);
+    graph.connect_similar_products(3, 6, 0.3);
+
+    let removed = graph.prune_to_top_k_per_node(2);
+    assert_eq!(removed, 7 - graph.edge_count());
+    for id in 1..=6 {
+        assert!(graph.get_connections(id).len() <= 2);
+    }
+    assert!(graph.has_edge(1, 2));
+    assert!(graph.has_edge(1, 3));
+    assert!(!graph.has_edge(1, 5));
+}
+
+#[test]
+fn test_from_cooccurrence() {
+    let entries = [(1, 2, 40.0), (1, 3, 10.0), (2, 3, 20.0), (3, 4, 0.0), (4, 4, 5.0)];
+    let categories: HashMap<u64, String> = [
+        (1, "Electronics".to_string()),
+        (2, "Electronics".to_string()),
+        (3, "Books".to_string()),
+        (5, "Toys".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    let graph = RecommendationGraph::from_cooccurrence(&entries, RelationType::BoughtTogether, &categories);
+
+    assert_eq!(graph.product_count(), 5);
+    assert_eq!(graph.edge_count(), 4);
+    assert_eq!(graph.get_product_node(3).unwrap().category, "Books");
+    assert_eq!(graph.get_product_node(4).unwrap().category, "Other");
+
+    let weight = |a: u64, b: u64| {
+        graph
+            .get_connections(a)
+            .into_iter()
+            .find(|(id, _, _)| *id == b)
+            .map(|(_, weight, _)| weight)
+            .unwrap()
+    };
+    assert_eq!(weight(1, 2), 1.0);
+    assert_eq!(weight(1, 3), 0.25);
+    assert_eq!(weight(2, 3), 0.5);
+    assert_eq!(weight(3, 4), 0.0);
+    assert_eq!(graph.get_frequently_bought_together(1).len(), 2);
+}
+
+#[test]
+fn test_from_cooccurrence_symmetric_and_invalid_counts() {
+    let entries = [(1, 2, 8.0), (2, 1, 8.0), (2, 3, -4.0), (1, 3, f32::NAN), (3, 4, 2.0)];
+    let graph = RecommendationGraph::from_cooccurrence(&entries, RelationType::BoughtTogether, &HashMap::new());
+
+    assert_eq!(graph.product_count(), 4);
+    assert_eq!(graph.edge_count(), 2);
+    assert_eq!(graph.get_connections(1), vec![(2, 1.0, RelationType::BoughtTogether)]);
+    assert_eq!(graph.get_connections(3), vec![(4, 0.25, RelationType::BoughtTogether)]);
+}
+
+#[test]
+fn test_tick_decay_matches_single_decay() {
+    let build = || {
+        let mut graph = RecommendationGraph::new();
+        for id in 1..=3 {
+            graph.add_product(id, "Electronics".to_string());
+        }
+        graph.connect_bought_together(1, 2, 0.8);
+        graph.connect_similar_products(2, 3, 0.6);
+        graph
+    };
+    let weights = |graph: &RecommendationGraph| -> Vec<f32> {
+        graph.all_edges().iter().map(|(_, _, weight, _)| *weight).collect()
+    };
+
+    let mut ticked = build();
+    ticked.tick_at(1_000, 3_600);
+    assert_eq!(weights(&ticked), vec![0.8, 0.6]);
+    for now in [2_800, 4_600, 8_200] {
+        ticked.tick_at(now, 3_600);
+    }
+
+    let mut decayed = build();
+    decayed.decay_edges(7_200, 3_600);
+
+    for (a, b) in weights(&ticked).iter().zip(weights(&decayed)) {
+        assert!((a - b).abs() < 1e-6);
+    }
+    assert!((weights(&decayed)[0] - 0.2).abs() < 1e-6);
+
+    ticked.tick_at(5_000, 3_600);
+    for (a, b) in weights(&ticked).iter().zip(weights(&decayed)) {
+        assert!((a - b).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_recommendation_paths_chain() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    // 1 - 2 - 3, plus 1 - 4 - 3 as a weaker route and 5 hanging off 4
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_bought_together(2, 3, 0.8);
+    graph.connect_similar_products(1, 4, 0.3);
+    graph.connect_similar_products(4, 3, 0.2);
+    graph.connect_similar_products(4, 5, 0.5);
+
+    let paths = graph.recommendation_paths(1, 10);
+    let path_to = |id: u64| paths.iter().find(|(rec, _, _)| *rec == id).map(|(_, _, path)| path.clone());
+
+    assert_eq!(path_to(2), Some(vec![1, 2]));
+    assert_eq!(path_to(3), Some(vec![1, 2, 3]));
+    assert_eq!(path_to(5), Some(vec![1, 4, 5]));
+
+    let scores: Vec<(u64, f32)> = paths.iter().map(|(id, score, _)| (*id, *score)).collect();
+    assert_eq!(scores, graph.get_recommendations_depth_2(1, 10));
+}
+
+#[test]
+fn test_edges_iter_visits_each_edge_once() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=4 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    graph.connect_similar_products(1, 2, 0.9);
+    graph.connect_bought_together(2, 3, 0.7);
+    graph.connect_also_viewed(3, 4, 0.4);
+    graph.connect_same_brand(1, 4);
+
+    let mut pairs: Vec<(u64, u64)> = graph
+        .edges_iter()
+        .map(|(a, b, _, _)| (a.min(b), a.max(b)))
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+
+    assert_eq!(graph.edges_iter().count(), graph.edge_count());
+    assert_eq!(pairs, vec![(1, 2), (1, 4), (2, 3), (3, 4)]);
+
+    let total_weight: f32 = graph.edges_iter().map(|(_, _, weight, _)| weight).sum();
+    let expected: f32 = graph.all_edges().iter().map(|(_, _, weight, _)| weight).sum();
+    assert_eq!(total_weight, expected);
+    assert_eq!(
+        graph.edges_iter().filter(|(_, _, _, relation)| **relation == RelationType::AlsoViewed).count(),
+        1
+    );
+}
+
+#[test]
+fn test_recommendations_recency_weighted() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=4 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    let day = 86_400;
+    let now = 100 * day;
+    graph.add_edge_at(1, 2, 0.8, RelationType::BoughtTogether, now - 30 * day);
+    graph.add_edge_at(1, 3, 0.8, RelationType::BoughtTogether, now - day);
+    graph.add_edge(1, 4, 0.1, RelationType::BoughtTogether);
+
+    let ranked = graph.get_recommendations_recency_weighted(1, now, 10, 7 * day);
+    let ids: Vec<u64> = ranked.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids[0], 3);
+    assert!(ranked[0].1 > ranked[1].1);
+
+    // An undated edge keeps its full weight.
+    let undated = ranked.iter().find(|(id, _)| *id == 4).unwrap().1;
+    assert!((undated - 0.15).abs() < 1e-6);
+
+    // Without decay the two dated edges tie again.
+    let flat = graph.get_recommendations_recency_weighted(1, now, 10, 0);
+    assert_eq!(flat[0].1, flat[1].1);
+}
+
+#[test]
+fn test_pagerank_star() {
+    let mut graph = RecommendationGraph::new();
+
+    for id in 1..=5 {
+        graph.add_product(id, "Electronics".to_string());
+    }
+
+    // 1 is the hub of a star; 5 is isolated
+    for leaf in 2..=4 {
+        graph.connect_bought_together(1, leaf, 0.8);
+    }
+
+    let ranks = graph.pagerank(0.85, 100);
+    assert_eq!(ranks.len(), 5);
+    assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-6);
+    assert!(ranks[&1] > ranks[&2]);
+    assert!((ranks[&2] - ranks[&3]).abs() < 1e-9);
+    assert!(ranks[&5] < ranks[&2]);
+    assert!(RecommendationGraph::new().pagerank(0.85, 100).is_empty());
+}
+
+#[test]
+fn test_set_category() {
+    let mut graph = RecommendationGraph::new();
+    graph.add_product(1, "Electronics".to_string());
+
+    assert!(graph.set_category(1, "Books".to_string()));
+    assert_eq!(graph.get_product_node(1).unwrap().category, "Books");
+    assert!(!graph.set_category(2, "Books".to_string()));
+}
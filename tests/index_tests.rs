@@ -1,4 +1,6 @@
 use megastore_search::{Product, Category, ProductIndex};
+use megastore_search::models::normalize_term;
+use megastore_search::indexing::{edit_distance, CompletenessCriteria, ConsistencyError};
 
 fn create_test_product(id: u64, name: &str, brand: &str, category: Category) -> Product {
     let mut product = Product::new(
@@ -138,4 +140,524 @@ fn test_product_count() {
 
     index.remove_product(1);
     assert_eq!(index.product_count(), 1);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tag_vector() {
+    let mut index = ProductIndex::new();
+
+    let mut product = create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics);
+    product.add_tag("Gaming".to_string());
+    product.add_tag("laptop".to_string());
+    product.add_tag("rgb".to_string());
+    product.add_tag("portable".to_string());
+    index.add_product(product);
+
+    let vector = index.tag_vector(1);
+    assert_eq!(vector.len(), 4);
+    assert_eq!(vector.get("gaming"), Some(&0.25));
+    assert_eq!(vector.get("rgb"), Some(&0.25));
+
+    assert!(index.tag_vector(999).is_empty());
+}
+
+#[test]
+fn test_search_by_category_text() {
+    let mut index = ProductIndex::new();
+
+    index.add_product(create_test_product(1, "Vase", "Brand", Category::HomeDecor));
+    index.add_product(create_test_product(2, "Shirt", "Brand", Category::Clothing));
+
+    assert_eq!(index.search_by_category_text("decor"), vec![1]);
+    assert_eq!(index.search_by_category_text("CLOTHING"), vec![2]);
+    assert!(index.search_by_category_text("books").is_empty());
+}
+
+#[test]
+fn test_sorted_by_price_and_rating() {
+    let mut index = ProductIndex::new();
+
+    let mut cheap = create_test_product(1, "Cheap", "Brand", Category::Electronics);
+    cheap.price = 10.0;
+    cheap.rating = 4.8;
+    let mut mid = create_test_product(2, "Mid", "Brand", Category::Electronics);
+    mid.price = 50.0;
+    mid.rating = 3.5;
+    let mut pricey = create_test_product(3, "Pricey", "Brand", Category::Electronics);
+    pricey.price = 250.0;
+    pricey.rating = 4.1;
+    let mut broken = create_test_product(4, "Broken", "Brand", Category::Electronics);
+    broken.price = f64::NAN;
+    broken.rating = 1.0;
+
+    index.add_product(pricey);
+    index.add_product(broken);
+    index.add_product(cheap);
+    index.add_product(mid);
+
+    let by_price: Vec<u64> = index.sorted_by_price().iter().map(|p| p.id).collect();
+    assert_eq!(by_price, vec![1, 2, 3, 4]);
+
+    let by_rating: Vec<u64> = index.sorted_by_rating().iter().map(|p| p.id).collect();
+    assert_eq!(by_rating, vec![4, 2, 3, 1]);
+
+    let by_stock_then_name: Vec<u64> = index
+        .sorted_by(|p| (p.stock, p.name.clone()))
+        .iter()
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(by_stock_then_name, vec![4, 1, 2, 3]);
+}
+
+#[test]
+fn test_price_quantile_skips_nan_prices() {
+    let mut index = ProductIndex::new();
+
+    let mut broken = create_test_product(1, "Broken", "Brand", Category::Electronics);
+    broken.price = f64::NAN;
+    index.add_product(broken);
+    assert_eq!(index.price_quantile(0.5), None);
+
+    for (id, price) in [(2, 10.0), (3, 30.0), (4, 20.0)] {
+        let mut product = create_test_product(id, "Item", "Brand", Category::Electronics);
+        product.price = price;
+        index.add_product(product);
+    }
+
+    assert_eq!(index.price_quantile(0.5), Some(20.0));
+    assert_eq!(index.price_quartiles(), Some((15.0, 20.0, 25.0)));
+}
+
+#[test]
+fn test_tag_usage() {
+    let mut index = ProductIndex::new();
+
+    let mut product1 = create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics);
+    product1.add_tag("gaming".to_string());
+    let mut product2 = create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics);
+    product2.add_tag("Gaming".to_string());
+
+    index.add_product(product1);
+    index.add_product(product2);
+
+    assert_eq!(index.tag_usage("GAMING"), 2);
+    assert_eq!(index.tag_usage("office"), 0);
+}
+
+#[test]
+fn test_search_by_tag_prefix() {
+    let mut index = ProductIndex::new();
+
+    let mut product1 = create_test_product(1, "Laptop", "Asus", Category::Electronics);
+    product1.add_tag("gaming".to_string());
+    let mut product2 = create_test_product(2, "Console", "Sony", Category::Electronics);
+    product2.add_tag("Gamepad".to_string());
+    let mut product3 = create_test_product(3, "Chair", "Ikea", Category::HomeDecor);
+    product3.add_tag("office".to_string());
+
+    index.add_product(product1);
+    index.add_product(product2);
+    index.add_product(product3);
+
+    let mut results = index.search_by_tag_prefix("GAM");
+    results.sort();
+    assert_eq!(results, vec![1, 2]);
+    assert_eq!(index.search_by_tag_prefix("gaming"), vec![1]);
+    assert!(index.search_by_tag_prefix("gamer").is_empty());
+}
+
+#[test]
+fn test_analyze_query() {
+    let mut index = ProductIndex::new();
+    index.add_product(create_test_product(1, "Camiseta Nike Dri-Fit", "Nike", Category::Clothing));
+
+    let tokens = index.analyze_query("  Dri-Fit   Camiseta ");
+    assert_eq!(tokens, vec!["dri-fit".to_string(), "camiseta".to_string()]);
+
+    // The analyzed tokens are exactly the keys looked up by name search
+    for token in &tokens {
+        assert_eq!(index.search_by_name(token), vec![1]);
+    }
+    assert!(index.search_by_name("fit").is_empty());
+}
+
+#[test]
+fn test_search_by_categories() {
+    let mut index = ProductIndex::new();
+
+    index.add_product(create_test_product(1, "Laptop", "Brand", Category::Electronics));
+    index.add_product(create_test_product(2, "Shirt", "Brand", Category::Clothing));
+    index.add_product(create_test_product(3, "Phone", "Brand", Category::Electronics));
+    index.add_product(create_test_product(4, "Novel", "Brand", Category::Books));
+
+    let mut results = index.search_by_categories(&[
+        Category::Electronics,
+        Category::Clothing,
+        Category::Electronics,
+    ]);
+    results.sort();
+    assert_eq!(results, vec![1, 2, 3]);
+
+    assert!(index.search_by_categories(&[]).is_empty());
+}
+
+#[test]
+fn test_content_hash_order_independent() {
+    let products = [
+        create_test_product(1, "Laptop", "Dell", Category::Electronics),
+        create_test_product(2, "Shirt", "Nike", Category::Clothing),
+        create_test_product(3, "Phone", "Apple", Category::Electronics),
+    ];
+
+    let mut forward = ProductIndex::new();
+    for product in products.iter().cloned() {
+        forward.add_product(product);
+    }
+
+    let mut reverse = ProductIndex::new();
+    for product in products.iter().rev().cloned() {
+        reverse.add_product(product);
+    }
+
+    assert_eq!(forward.content_hash(), reverse.content_hash());
+
+    let mut changed = products[1].clone();
+    changed.stock += 1;
+    reverse.update_product(2, changed);
+    assert_ne!(forward.content_hash(), reverse.content_hash());
+}
+
+#[test]
+fn test_content_hash_is_stable() {
+    // Pinned value: content hashes are persisted, so this must only change
+    // alongside a deliberate format change.
+    let mut index = ProductIndex::new();
+    index.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics));
+    assert_eq!(index.content_hash(), 0xa881_ef4e_8f89_363a);
+    assert_eq!(ProductIndex::new().content_hash(), 0);
+}
+
+#[test]
+fn test_min_term_length() {
+    let mut index = ProductIndex::new();
+    index.add_product(create_test_product(1, "Vitamin C Serum", "X", Category::Beauty));
+    index.add_product(create_test_product(2, "Smart TV", "LG", Category::Electronics));
+
+    assert!(index.search_by_name("c").is_empty());
+    assert_eq!(index.search_by_name("vitamin c"), vec![1]);
+    assert_eq!(index.search_by_name("tv"), vec![2]);
+    assert!(index.search_by_brand("x").is_empty());
+    assert_eq!(index.search_by_brand("lg"), vec![2]);
+    assert_eq!(index.analyze_query("a tv"), vec!["tv".to_string()]);
+
+    let mut strict = ProductIndex::new().with_min_term_length(3);
+    strict.add_product(create_test_product(2, "Smart TV", "LG", Category::Electronics));
+    assert!(strict.search_by_name("tv").is_empty());
+    assert!(strict.search_by_brand("lg").is_empty());
+    assert_eq!(strict.search_by_name("smart"), vec![2]);
+
+    assert!(strict.remove_product(2).is_some());
+    assert!(strict.search_by_name("smart").is_empty());
+}
+
+#[test]
+fn test_sorted_by_bayesian_rating() {
+    let mut index = ProductIndex::new();
+
+    let mut lucky = create_test_product(1, "Lucky", "Brand", Category::Electronics);
+    lucky.rating = 5.0;
+    lucky.review_count = 1;
+    let mut proven = create_test_product(2, "Proven", "Brand", Category::Electronics);
+    proven.rating = 4.8;
+    proven.review_count = 50;
+
+    index.add_product(lucky);
+    index.add_product(proven);
+
+    let by_raw: Vec<u64> = index.sorted_by_rating().iter().map(|p| p.id).collect();
+    assert_eq!(by_raw, vec![2, 1]);
+
+    let by_bayesian: Vec<u64> = index.sorted_by_bayesian_rating(3.5, 10.0).iter().map(|p| p.id).collect();
+    assert_eq!(by_bayesian, vec![1, 2]);
+}
+
+#[test]
+fn test_verify_consistency_detects_overwrite() {
+    let mut index = ProductIndex::new();
+
+    let mut product = create_test_product(1, "Old Laptop", "Dell", Category::Electronics);
+    product.add_tag("laptop".to_string());
+    index.add_product(product);
+    index.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics));
+    assert_eq!(index.verify_consistency(), Ok(()));
+
+    index.update_product(2, create_test_product(2, "Wireless Mouse", "Logitech", Category::Electronics));
+    assert_eq!(index.verify_consistency(), Ok(()));
+
+    // Re-adding an existing id overwrites the product without cleaning up its old buckets
+    index.add_product(create_test_product(1, "New Laptop", "Asus", Category::Electronics));
+
+    let errors = index.verify_consistency().unwrap_err();
+    assert!(errors.contains(&ConsistencyError::StaleEntry {
+        index: "name",
+        key: "old".to_string(),
+        id: 1,
+    }));
+    assert!(errors.contains(&ConsistencyError::StaleEntry {
+        index: "brand",
+        key: "dell".to_string(),
+        id: 1,
+    }));
+    assert!(errors.contains(&ConsistencyError::StaleEntry {
+        index: "tag",
+        key: "laptop".to_string(),
+        id: 1,
+    }));
+    assert!(errors.iter().all(|error| !matches!(error, ConsistencyError::DanglingId { .. })));
+}
+
+#[test]
+fn test_category_aggregates() {
+    let mut index = ProductIndex::new();
+
+    let mut laptop = create_test_product(1, "Laptop", "Dell", Category::Electronics);
+    laptop.price = 1000.0;
+    laptop.rating = 4.5;
+    let mut mouse = create_test_product(2, "Mouse", "Logitech", Category::Electronics);
+    mouse.price = 50.0;
+    mouse.rating = 4.0;
+    let mut monitor = create_test_product(3, "Monitor", "LG", Category::Electronics);
+    monitor.price = 300.0;
+    monitor.rating = 3.5;
+    let mut shirt = create_test_product(4, "Shirt", "Nike", Category::Clothing);
+    shirt.price = 40.0;
+    shirt.rating = 4.0;
+    let mut jacket = create_test_product(5, "Jacket", "Adidas", Category::Clothing);
+    jacket.price = 120.0;
+    jacket.rating = 5.0;
+
+    for product in [laptop, mouse, monitor, shirt, jacket] {
+        index.add_product(product);
+    }
+
+    let aggregates = index.category_aggregates();
+    assert_eq!(aggregates.len(), 2);
+
+    let electronics = &aggregates[&Category::Electronics];
+    assert_eq!(electronics.count, 3);
+    assert!((electronics.avg_price - 450.0).abs() < 1e-9);
+    assert!((electronics.avg_rating - 4.0).abs() < 1e-9);
+    assert_eq!(electronics.min_price, 50.0);
+    assert_eq!(electronics.max_price, 1000.0);
+
+    let clothing = &aggregates[&Category::Clothing];
+    assert_eq!(clothing.count, 2);
+    assert!((clothing.avg_price - 80.0).abs() < 1e-9);
+    assert!((clothing.avg_rating - 4.5).abs() < 1e-9);
+    assert_eq!(clothing.min_price, 40.0);
+    assert_eq!(clothing.max_price, 120.0);
+}
+
+#[test]
+fn test_edit_distance_and_correct_term() {
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("mouse", "mouse"), 0);
+
+    let mut index = ProductIndex::new();
+    let mut product = create_test_product(1, "Wireless Keyboard", "Logitech", Category::Electronics);
+    product.add_tag("gaming".to_string());
+    index.add_product(product);
+
+    assert_eq!(index.correct_term("keybaord"), Some("keyboard".to_string()));
+    assert_eq!(index.correct_term("logitek"), Some("logitech".to_string()));
+    assert_eq!(index.correct_term("gamin"), Some("gaming".to_string()));
+    assert_eq!(index.correct_term("keyboard"), None);
+    assert_eq!(index.correct_term("banana"), None);
+}
+
+#[test]
+fn test_price_quantiles() {
+    let mut index = ProductIndex::new();
+    assert_eq!(index.price_quantile(0.5), None);
+    assert_eq!(index.price_quartiles(), None);
+
+    for (id, price) in [(1, 50.0), (2, 10.0), (3, 40.0), (4, 20.0), (5, 30.0)] {
+        let mut product = create_test_product(id, "Item", "Brand", Category::Electronics);
+        product.price = price;
+        index.add_product(product);
+    }
+
+    assert_eq!(index.price_quantile(0.0), Some(10.0));
+    assert_eq!(index.price_quantile(0.5), Some(30.0));
+    assert_eq!(index.price_quantile(1.0), Some(50.0));
+    assert_eq!(index.price_quantile(0.125), Some(15.0));
+    assert_eq!(index.price_quartiles(), Some((20.0, 30.0, 40.0)));
+
+    assert_eq!(index.price_quantile(-0.1), None);
+    assert_eq!(index.price_quantile(1.5), None);
+    assert_eq!(index.price_quantile(f64::NAN), None);
+}
+
+#[test]
+fn test_find_by_predicate() {
+    let mut index = ProductIndex::new();
+    for (id, price) in [(1, 100.0), (2, 99.9), (3, 250.0), (4, 19.99)] {
+        let mut product = create_test_product(id, "Item", "Brand", Category::Electronics);
+        product.price = price;
+        index.add_product(product);
+    }
+
+    let not_round = |product: &Product| product.price.fract() != 0.0;
+    let found: Vec<u64> = index.find(not_round).iter().map(|p| p.id).collect();
+
+    let expected: Vec<u64> = index
+        .all_products()
+        .iter()
+        .filter(|product| not_round(product))
+        .map(|p| p.id)
+        .collect();
+    assert_eq!(found, expected);
+    assert_eq!(found, vec![2, 4]);
+    assert!(index.find(|product| product.price > 1000.0).is_empty());
+}
+
+#[test]
+fn test_export_import_json_round_trip() {
+    let mut index = ProductIndex::new();
+    let mut laptop = create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics);
+    laptop.add_tag("gaming".to_string());
+    laptop.sale_price = Some(89.9);
+    index.add_product(laptop);
+    index.add_product(create_test_product(2, "Linen Shirt", "Zara", Category::Other("Linen & Co".to_string())));
+
+    let mut buffer = Vec::new();
+    index.export_json(&mut buffer).unwrap();
+    let json = String::from_utf8(buffer.clone()).unwrap();
+    assert!(json.starts_with('[') && json.ends_with(']'));
+
+    let mut restored = ProductIndex::new();
+    assert_eq!(restored.import_json(buffer.as_slice()).unwrap(), 2);
+    assert_eq!(restored.content_hash(), index.content_hash());
+    assert_eq!(restored.get_product(1).unwrap().tags, vec!["gaming".to_string()]);
+    assert_eq!(restored.search_by_tag("gaming"), vec![1]);
+
+    let mut empty = Vec::new();
+    ProductIndex::new().export_json(&mut empty).unwrap();
+    assert_eq!(empty, b"[]");
+}
+
+#[test]
+fn test_whitespace_normalized_in_index() {
+    let mut index = ProductIndex::new();
+    let mut product = create_test_product(1, "Gaming  Laptop ", "  Dell ", Category::Electronics);
+    product.add_tag(" Ultra   Portable ".to_string());
+    index.add_product(product);
+
+    assert_eq!(index.search_by_brand("dell"), vec![1]);
+    assert_eq!(index.search_by_brand(" Dell"), vec![1]);
+    assert_eq!(index.search_by_tag("ultra portable"), vec![1]);
+    assert_eq!(index.analyze_query("  Gaming   Laptop "), vec!["gaming", "laptop"]);
+    assert_eq!(index.search_by_name("gaming laptop"), vec![1]);
+    assert_eq!(index.all_tags(), vec!["ultra portable".to_string()]);
+    assert_eq!(index.verify_consistency(), Ok(()));
+
+    // Stored fields keep their original spelling for display
+    assert_eq!(index.get_product(1).unwrap().brand, "  Dell ");
+    assert_eq!(normalize_term("  Gaming \t Laptop "), "gaming laptop");
+
+    index.remove_product(1);
+    assert!(index.search_by_brand("dell").is_empty());
+    assert!(index.all_tags().is_empty());
+}
+
+#[test]
+fn test_incomplete_products() {
+    let mut index = ProductIndex::new();
+
+    let mut complete = create_test_product(1, "Laptop", "Dell", Category::Electronics);
+    complete.add_tag("portable".to_string());
+    let untagged = create_test_product(2, "Mouse", "Logitech", Category::Electronics);
+    let mut unbranded = create_test_product(3, "Cable", " Generic ", Category::Electronics);
+    unbranded.add_tag("usb".to_string());
+    let mut unrated = create_test_product(4, "Hub", "Anker", Category::Electronics);
+    unrated.add_tag("usb".to_string());
+    unrated.rating = 0.0;
+    unrated.description = "  ".to_string();
+
+    for product in [complete, untagged, unbranded, unrated] {
+        index.add_product(product);
+    }
+
+    assert_eq!(index.incomplete_products(CompletenessCriteria::none().with_tags()), vec![2]);
+    assert_eq!(index.incomplete_products(CompletenessCriteria::none().with_brand()), vec![3]);
+    assert_eq!(
+        index.incomplete_products(CompletenessCriteria::none().with_rating().with_description()),
+        vec![4]
+    );
+    assert_eq!(index.incomplete_products(CompletenessCriteria::all()), vec![2, 3, 4]);
+    assert!(index.incomplete_products(CompletenessCriteria::none()).is_empty());
+}
+
+#[cfg(feature = "tag-sketch")]
+#[test]
+fn test_approx_tag_frequency_within_error_bound() {
+    use megastore_search::indexing::{DEFAULT_SKETCH_DELTA, DEFAULT_SKETCH_EPSILON};
+
+    let mut index = ProductIndex::new();
+
+    for id in 0..2000u64 {
+        let mut product = create_test_product(id, "Item", "Acme", Category::Electronics);
+        product.add_tag(format!("tag{}", id % 50));
+        if id % 4 == 0 {
+            product.add_tag("Hot".to_string());
+        }
+        index.add_product(product);
+    }
+
+    // Each estimate is at least the exact count, and exceeds it by more than
+    // epsilon * total only with probability delta.
+    let total_assignments = 2000 + 500;
+    let bound = (DEFAULT_SKETCH_EPSILON * total_assignments as f64).ceil() as u64;
+    let tags = index.all_tags();
+    let mut over_bound = 0;
+    for tag in &tags {
+        let exact = index.tag_usage(tag) as u64;
+        let estimate = index.approx_tag_frequency(tag);
+        assert!(estimate >= exact, "{} underestimated", tag);
+        if estimate > exact + bound {
+            over_bound += 1;
+        }
+    }
+    assert!(over_bound as f64 <= (DEFAULT_SKETCH_DELTA * tags.len() as f64).ceil());
+    assert!(index.approx_tag_frequency(" HOT ") >= 500);
+
+    index.remove_product(0);
+    index.rename_tag("tag1", "tag2");
+    assert!(index.approx_tag_frequency("hot") >= 499);
+    assert!(index.approx_tag_frequency("tag2") >= 80);
+    assert!(index.approx_tag_frequency("tag1") <= bound);
+}
+
+#[test]
+fn test_reassign_category() {
+    let mut index = ProductIndex::new();
+    let mut watch = create_test_product(1, "Smart Watch", "Acme", Category::Clothing);
+    watch.add_tag("wearable".to_string());
+    index.add_product(watch);
+    index.add_product(create_test_product(2, "Laptop", "Dell", Category::Electronics));
+
+    assert!(index.reassign_category(1, Category::Electronics));
+
+    assert!(index.search_by_category(&Category::Clothing).is_empty());
+    assert!(!index.category_aggregates().contains_key(&Category::Clothing));
+    let mut electronics = index.search_by_category(&Category::Electronics);
+    electronics.sort();
+    assert_eq!(electronics, vec![1, 2]);
+    assert_eq!(index.get_product(1).unwrap().category, Category::Electronics);
+
+    assert_eq!(index.search_by_name("watch"), vec![1]);
+    assert_eq!(index.search_by_tag("wearable"), vec![1]);
+    assert!(index.verify_consistency().is_ok());
+    assert!(!index.reassign_category(99, Category::Books));
+}
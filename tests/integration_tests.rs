@@ -85,11 +85,11 @@ fn setup_test_catalog() -> SearchEngine {
     engine.add_product(office_laptop);
 
     // Set up relationships
-    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether); // Gaming laptop + mouse
-    engine.add_product_relation(1, 4, 0.8, RelationType::BoughtTogether); // Gaming laptop + keyboard
-    engine.add_product_relation(3, 4, 0.7, RelationType::BoughtTogether); // Mouse + keyboard
-    engine.add_product_relation(1, 2, 0.8, RelationType::Similar); // Similar gaming laptops
-    engine.add_product_relation(2, 5, 0.3, RelationType::SameBrand); // Dell laptops
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap(); // Gaming laptop + mouse
+    engine.add_product_relation(1, 4, 0.8, RelationType::BoughtTogether).unwrap(); // Gaming laptop + keyboard
+    engine.add_product_relation(3, 4, 0.7, RelationType::BoughtTogether).unwrap(); // Mouse + keyboard
+    engine.add_product_relation(1, 2, 0.8, RelationType::Similar).unwrap(); // Similar gaming laptops
+    engine.add_product_relation(2, 5, 0.3, RelationType::SameBrand).unwrap(); // Dell laptops
 
     engine
 }
@@ -303,7 +303,7 @@ fn test_performance_with_larger_dataset() {
     // Add some relationships
     for i in 1..=50 {
         if i + 1 <= 100 {
-            engine.add_product_relation(i, i + 1, 0.5, RelationType::Similar);
+            engine.add_product_relation(i, i + 1, 0.5, RelationType::Similar).unwrap();
         }
     }
 
@@ -1,4 +1,5 @@
 use megastore_search::{Product, Category};
+use megastore_search::models::{SearchFields, StockError};
 
 #[test]
 fn test_product_creation() {
@@ -100,4 +101,190 @@ fn test_category_display() {
     assert_eq!(Category::Food.to_string(), "Food");
     assert_eq!(Category::HomeDecor.to_string(), "Home & Decor");
     assert_eq!(Category::Other("Custom".to_string()).to_string(), "Custom");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_reserve_and_release_stock() {
+    let mut product = Product::new(
+        1,
+        "Mouse".to_string(),
+        "Desc".to_string(),
+        "Brand".to_string(),
+        Category::Electronics,
+        50.0,
+    );
+    product.stock = 5;
+
+    assert_eq!(
+        product.reserve_stock(6),
+        Err(StockError::InsufficientStock { requested: 6, available: 5 })
+    );
+    assert_eq!(product.stock, 5);
+
+    assert!(product.reserve_stock(5).is_ok());
+    assert_eq!(product.stock, 0);
+
+    product.release_stock(3);
+    assert_eq!(product.stock, 3);
+
+    product.release_stock(u32::MAX);
+    assert_eq!(product.stock, u32::MAX);
+}
+
+#[test]
+fn test_price_cents_and_format() {
+    let mut product = Product::new(
+        1,
+        "Notebook".to_string(),
+        "Desc".to_string(),
+        "Brand".to_string(),
+        Category::Electronics,
+        2800.0,
+    );
+
+    assert_eq!(product.price_cents(), 280_000);
+    assert_eq!(product.format_price("R$"), "R$ 2800.00");
+
+    product.price = 2800.00001;
+    assert_eq!(product.price_cents(), 280_000);
+
+    product.price = 19.995;
+    assert_eq!(product.price_cents(), 2000);
+
+    product.price = 0.1 + 0.2;
+    assert_eq!(product.price_cents(), 30);
+    assert_eq!(product.format_price("$"), "$ 0.30");
+}
+
+#[test]
+fn test_product_summary() {
+    let mut product = Product::new(
+        7,
+        "Notebook Dell".to_string(),
+        "Long description that is not part of the summary".to_string(),
+        "Dell".to_string(),
+        Category::Electronics,
+        3500.0,
+    );
+    product.rating = 4.5;
+    product.add_tag("laptop".to_string());
+
+    let summary = product.summary();
+    assert_eq!(summary.id, 7);
+    assert_eq!(summary.name, "Notebook Dell");
+    assert_eq!(summary.brand, "Dell");
+    assert_eq!(summary.price, 3500.0);
+    assert_eq!(summary.rating, 4.5);
+
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(!json.contains("description"));
+    assert!(!json.contains("tags"));
+}
+
+#[test]
+fn test_search_score_fields() {
+    let mut product = Product::new(
+        1,
+        "Laptop".to_string(),
+        "Portable laptop".to_string(),
+        "Brand".to_string(),
+        Category::Electronics,
+        1000.0,
+    );
+    product.add_tag("laptop".to_string());
+
+    let all = product.search_score_fields("laptop", &SearchFields::all());
+    assert_eq!(all, product.search_score("laptop"));
+    assert_eq!(all, 20.0);
+
+    assert_eq!(product.search_score_fields("laptop", &SearchFields::none().with_name()), 14.0);
+    assert_eq!(product.search_score_fields("laptop", &SearchFields::none()), 0.0);
+}
+
+#[test]
+fn test_compare_products() {
+    let mut gaming = Product::new(
+        1,
+        "Gaming Laptop".to_string(),
+        "Desc".to_string(),
+        "Dell".to_string(),
+        Category::Electronics,
+        1500.0,
+    );
+    gaming.rating = 4.6;
+    gaming.add_tag("laptop".to_string());
+    gaming.add_tag("gaming".to_string());
+    gaming.add_tag("rgb".to_string());
+
+    let mut office = Product::new(
+        2,
+        "Office Laptop".to_string(),
+        "Desc".to_string(),
+        "dell".to_string(),
+        Category::Electronics,
+        900.0,
+    );
+    office.rating = 4.1;
+    office.add_tag("Laptop".to_string());
+    office.add_tag("office".to_string());
+
+    let comparison = gaming.compare(&office);
+    assert_eq!(comparison.shared_tags, vec!["laptop".to_string()]);
+    assert_eq!(comparison.unique_tags, vec!["gaming".to_string(), "rgb".to_string()]);
+    assert_eq!(comparison.other_unique_tags, vec!["office".to_string()]);
+    assert!(comparison.price_difference > 0.0);
+    assert_eq!(comparison.price_difference, 600.0);
+    assert!((comparison.rating_difference - 0.5).abs() < 1e-6);
+    assert!(comparison.same_brand);
+    assert!(comparison.same_category);
+
+    assert!(office.compare(&gaming).price_difference < 0.0);
+}
+
+#[test]
+fn test_bayesian_rating() {
+    let mut single_review = Product::new(
+        1,
+        "New Gadget".to_string(),
+        "Desc".to_string(),
+        "Brand".to_string(),
+        Category::Electronics,
+        50.0,
+    );
+    single_review.rating = 5.0;
+    single_review.review_count = 1;
+
+    let mut well_reviewed = single_review.clone();
+    well_reviewed.id = 2;
+    well_reviewed.rating = 4.8;
+    well_reviewed.review_count = 50;
+
+    let single = single_review.bayesian_rating(3.5, 10.0);
+    let many = well_reviewed.bayesian_rating(3.5, 10.0);
+    assert!(single < many);
+    assert!((single - 40.0 / 11.0).abs() < 1e-5);
+
+    single_review.review_count = 0;
+    assert_eq!(single_review.bayesian_rating(3.5, 10.0), 3.5);
+}
+
+#[test]
+fn test_price_for_quantity() {
+    let mut product = Product::new(
+        1,
+        "Printer Paper".to_string(),
+        "A4 ream".to_string(),
+        "Chamex".to_string(),
+        Category::Other("Office".to_string()),
+        30.0,
+    );
+    assert_eq!(product.price_for_quantity(100), 30.0);
+
+    product.price_tiers = vec![(50, 24.0), (10, 27.0), (100, 21.0)];
+    assert_eq!(product.price_for_quantity(1), 30.0);
+    assert_eq!(product.price_for_quantity(9), 30.0);
+    assert_eq!(product.price_for_quantity(10), 27.0);
+    assert_eq!(product.price_for_quantity(49), 27.0);
+    assert_eq!(product.price_for_quantity(50), 24.0);
+    assert_eq!(product.price_for_quantity(500), 21.0);
+}
@@ -1,6 +1,10 @@
 use megastore_search::{Product, Category, SearchEngine, SearchFilters};
-use megastore_search::search::MatchType;
+use megastore_search::search::{filter_by_match_type, MatchType, RelationIssue, ScoreBreakdown, ScoreScale, ScoringConfig, SearchFields, SearchError, SearchObserver, SearchOverrides, SearchResult, SortBy, SortKey, sort_results};
 use megastore_search::graph::RelationType;
+use megastore_search::models::{StockError, Variant};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 fn create_test_product(id: u64, name: &str, brand: &str, category: Category, price: f64, rating: f32) -> Product {
     let mut product = Product::new(
@@ -142,7 +146,7 @@ fn test_add_product_relation() {
     engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
     engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0));
 
-    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether);
+    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether).unwrap();
 
     let (_, edges) = engine.get_graph_stats();
     assert_eq!(edges, 1);
@@ -156,8 +160,8 @@ fn test_get_recommendations_for_product() {
     engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0));
     engine.add_product(create_test_product(3, "Keyboard", "Logitech", Category::Electronics, 80.0, 4.2));
 
-    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether);
-    engine.add_product_relation(1, 3, 0.6, RelationType::Similar);
+    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.6, RelationType::Similar).unwrap();
 
     let recommendations = engine.get_recommendations_for_product(1, 5);
     assert_eq!(recommendations.len(), 2);
@@ -172,7 +176,7 @@ fn test_search_with_recommendations() {
     engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
     engine.add_product(create_test_product(3, "Office Laptop", "Dell", Category::Electronics, 800.0, 4.0));
 
-    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether);
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
 
     let results_with_rec = engine.search_with_recommendations("laptop", true, 5);
     let results_without_rec = engine.search_with_recommendations("laptop", false, 5);
@@ -188,8 +192,8 @@ fn test_search_similar_products() {
     engine.add_product(create_test_product(2, "Laptop B", "Brand", Category::Electronics, 1100.0, 4.3));
     engine.add_product(create_test_product(3, "Mouse", "Brand", Category::Electronics, 50.0, 4.0));
 
-    engine.add_product_relation(1, 2, 0.8, RelationType::Similar);
-    engine.add_product_relation(1, 3, 0.6, RelationType::BoughtTogether);
+    engine.add_product_relation(1, 2, 0.8, RelationType::Similar).unwrap();
+    engine.add_product_relation(1, 3, 0.6, RelationType::BoughtTogether).unwrap();
 
     let similar = engine.search_similar_products(1);
     assert_eq!(similar.len(), 1);
@@ -204,7 +208,7 @@ fn test_hybrid_search() {
     engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
     engine.add_product(create_test_product(3, "Office Laptop", "Dell", Category::Electronics, 800.0, 4.0));
 
-    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether);
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
 
     let filters = SearchFilters::new().category(Category::Electronics);
 
@@ -212,4 +216,1725 @@ fn test_hybrid_search() {
     let regular_results = engine.hybrid_search(Some("gaming"), &filters, false);
 
     assert!(hybrid_results.len() >= regular_results.len());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_search_filters_exclude_ids() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
+    engine.add_product(create_test_product(3, "Gaming Keyboard", "Logitech", Category::Electronics, 90.0, 4.1));
+
+    let seen: HashSet<u64> = [1, 3].into_iter().collect();
+    let filters = SearchFilters::new()
+        .category(Category::Electronics)
+        .exclude_ids(seen);
+
+    let results = engine.search_with_filters(Some("gaming"), &filters);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].product.id, 2);
+
+    let browse_results = engine.search_with_filters(None, &filters);
+    assert!(browse_results.iter().all(|r| r.product.id != 1 && r.product.id != 3));
+}
+
+#[test]
+fn test_infer_categories() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Office Laptop", "Dell", Category::Electronics, 800.0, 4.0));
+    engine.add_product(create_test_product(3, "Laptop Sleeve", "Targus", Category::Other("Accessories".to_string()), 30.0, 4.1));
+    engine.add_product(create_test_product(4, "Cotton T-Shirt", "Nike", Category::Clothing, 25.0, 4.2));
+
+    let categories = engine.infer_categories("laptop");
+    assert_eq!(categories.len(), 2);
+    assert_eq!(categories[0].0, Category::Electronics);
+    assert!((categories[0].1 - 2.0 / 3.0).abs() < 1e-9);
+    assert!(categories.iter().all(|(category, _)| *category != Category::Clothing));
+
+    assert!(engine.infer_categories("nonexistent").is_empty());
+}
+
+#[test]
+fn test_search_filters_query_string_round_trip() {
+    let filters = SearchFilters::new()
+        .price_range(100.0, 499.99)
+        .min_rating(4.5)
+        .category(Category::HomeDecor)
+        .brand("Black & Decker".to_string())
+        .add_tag("gaming".to_string())
+        .add_tag("rgb lights".to_string())
+        .in_stock_only()
+        .exclude_ids([7, 3].into_iter().collect());
+
+    let query_string = filters.to_query_string();
+    assert!(query_string.starts_with("price=100-499.99&rating=4.5"));
+
+    let parsed = SearchFilters::from_query_string(&query_string).unwrap();
+    assert_eq!(parsed, filters);
+
+    let only_max = SearchFilters::from_query_string("price=-50").unwrap();
+    assert_eq!(only_max.min_price, None);
+    assert_eq!(only_max.max_price, Some(50.0));
+
+    assert_eq!(SearchFilters::from_query_string("").unwrap(), SearchFilters::new());
+    assert!(SearchFilters::from_query_string("rating=high").is_err());
+    assert!(SearchFilters::from_query_string("color=red").is_err());
+}
+
+#[test]
+fn test_search_dedup() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Wireless Mouse", "Logitech", Category::Electronics, 40.0, 4.0));
+    engine.add_product(create_test_product(2, "Wireless  mouse", "logitech ", Category::Electronics, 38.0, 4.6));
+    engine.add_product(create_test_product(3, "Wireless Mouse", "Microsoft", Category::Electronics, 35.0, 4.1));
+
+    assert_eq!(engine.basic_search("wireless").len(), 3);
+
+    let results = engine.search_dedup("wireless");
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().any(|r| r.product.id == 2));
+    assert!(results.iter().any(|r| r.product.id == 3));
+    assert!(results.iter().all(|r| r.product.id != 1));
+}
+
+#[test]
+fn test_nearest_by_tags() {
+    let mut engine = SearchEngine::new();
+
+    let tagged = |id: u64, name: &str, tags: &[&str]| {
+        let mut product = create_test_product(id, name, "Brand", Category::Electronics, 100.0, 4.0);
+        for tag in tags {
+            product.add_tag(tag.to_string());
+        }
+        product
+    };
+
+    engine.add_product(tagged(1, "Gaming Laptop", &["gaming", "laptop", "rgb"]));
+    engine.add_product(tagged(2, "Gaming Notebook", &["gaming", "laptop", "rgb"]));
+    engine.add_product(tagged(3, "Gaming Mouse", &["gaming", "mouse"]));
+    engine.add_product(tagged(4, "Office Chair", &["office", "chair"]));
+
+    let nearest = engine.nearest_by_tags(1, 5);
+    assert_eq!(nearest.len(), 2);
+    assert_eq!(nearest[0].product.id, 2);
+    assert!((nearest[0].score - 1.0).abs() < 1e-9);
+    assert!(nearest[1].score < nearest[0].score);
+    assert!(nearest.iter().all(|r| r.product.id != 1 && r.product.id != 4));
+}
+
+struct CapturingObserver {
+    calls: Arc<Mutex<Vec<(String, usize)>>>,
+}
+
+impl SearchObserver for CapturingObserver {
+    fn on_search(&self, query: &str, result_count: usize, _elapsed: Duration) {
+        self.calls.lock().unwrap().push((query.to_string(), result_count));
+    }
+}
+
+#[test]
+fn test_search_observer() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut engine = SearchEngine::new().with_observer(Box::new(CapturingObserver {
+        calls: Arc::clone(&calls),
+    }));
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
+    engine.add_product(create_test_product(3, "Office Chair", "Ikea", Category::HomeDecor, 150.0, 4.0));
+
+    engine.basic_search("gaming");
+    assert_eq!(*calls.lock().unwrap(), vec![("gaming".to_string(), 2)]);
+
+    engine.basic_search("chair");
+    engine.hybrid_search(Some("gaming"), &SearchFilters::new(), true);
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[1], ("chair".to_string(), 1));
+    assert_eq!(calls[2].0, "gaming");
+}
+
+#[test]
+fn test_basic_search_matches_category_name() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Cotton T-Shirt", "Nike", Category::Clothing, 25.0, 4.2));
+    engine.add_product(create_test_product(2, "Denim Jeans", "Levis", Category::Clothing, 60.0, 4.4));
+    engine.add_product(create_test_product(3, "Laptop", "Dell", Category::Electronics, 900.0, 4.5));
+
+    let results = engine.basic_search("clothing");
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.product.category == Category::Clothing));
+    assert!(results.iter().all(|r| r.score > 0.0));
+    assert!(results.iter().all(|r| matches!(r.match_type, MatchType::Category)));
+
+    let electronics_results = engine.search_with_filters(Some("electronics"), &SearchFilters::new());
+    assert_eq!(electronics_results.len(), 1);
+    assert_eq!(electronics_results[0].product.id, 3);
+}
+
+#[test]
+fn test_engine_reserve_stock() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+
+    assert!(engine.reserve(1, 4).is_ok());
+    assert_eq!(engine.get_product(1).unwrap().stock, 6);
+
+    assert_eq!(
+        engine.reserve(1, 7),
+        Err(StockError::InsufficientStock { requested: 7, available: 6 })
+    );
+    assert_eq!(engine.reserve(999, 1), Err(StockError::UnknownProduct(999)));
+
+    assert!(engine.release(1, 4).is_ok());
+    assert_eq!(engine.get_product(1).unwrap().stock, 10);
+}
+
+#[test]
+fn test_recommend_by_shared_tags() {
+    let mut engine = SearchEngine::new();
+
+    let tagged = |id: u64, name: &str, tags: &[&str]| {
+        let mut product = create_test_product(id, name, "Brand", Category::Electronics, 100.0, 4.0);
+        for tag in tags {
+            product.add_tag(tag.to_string());
+        }
+        product
+    };
+
+    engine.add_product(tagged(1, "Gaming Laptop", &["gaming", "rgb", "laptop"]));
+    engine.add_product(tagged(2, "Gaming Keyboard", &["gaming", "rgb"]));
+    engine.add_product(tagged(3, "Laptop Stand", &["laptop"]));
+    engine.add_product(tagged(4, "Desk Lamp", &["office"]));
+
+    let results = engine.recommend_by_shared_tags(1, 5);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2, 3]);
+    assert!(results[0].score > results[1].score);
+
+    assert!(engine.recommend_by_shared_tags(999, 5).is_empty());
+}
+
+#[test]
+fn test_filter_by_match_type() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
+    engine.add_product(create_test_product(3, "Laptop Cooling Pad", "Havit", Category::Electronics, 30.0, 4.0));
+
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+
+    let results = engine.hybrid_search(Some("gaming"), &SearchFilters::new(), true);
+    assert_eq!(results.len(), 3);
+
+    let direct = filter_by_match_type(
+        engine.hybrid_search(Some("gaming"), &SearchFilters::new(), true),
+        &[MatchType::Combined],
+    );
+    let recommended = filter_by_match_type(results, &[MatchType::Recommendation]);
+
+    assert_eq!(direct.len(), 2);
+    assert!(direct.iter().all(|r| r.product.name.contains("Gaming")));
+    assert_eq!(recommended.len(), 1);
+    assert_eq!(recommended[0].product.id, 3);
+}
+
+#[test]
+fn test_price_filter_boundaries_are_deterministic() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Cable", "Brand", Category::Electronics, 0.1 + 0.2, 4.0));
+    engine.add_product(create_test_product(2, "Adapter", "Brand", Category::Electronics, 2800.00001, 4.0));
+    engine.add_product(create_test_product(3, "Charger", "Brand", Category::Electronics, 0.29, 4.0));
+
+    let at_min = engine.search_by_price_range(0.3, 10.0);
+    assert_eq!(at_min.len(), 1);
+    assert_eq!(at_min[0].product.id, 1);
+
+    let at_max = engine.search_by_price_range(100.0, 2800.0);
+    assert_eq!(at_max.len(), 1);
+    assert_eq!(at_max[0].product.id, 2);
+}
+
+#[test]
+fn test_search_with_filters_matches_tag_prefix() {
+    let mut engine = SearchEngine::new();
+
+    let mut headset = create_test_product(1, "Wireless Headset", "HyperX", Category::Electronics, 90.0, 4.4);
+    headset.add_tag("gaming".to_string());
+    let mut chair = create_test_product(2, "Ergonomic Chair", "Ikea", Category::HomeDecor, 200.0, 4.1);
+    chair.add_tag("office".to_string());
+
+    engine.add_product(headset);
+    engine.add_product(chair);
+
+    let results = engine.search_with_filters(Some("gam"), &SearchFilters::new());
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].product.id, 1);
+}
+
+#[test]
+fn test_search_summaries() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
+
+    let results = engine.basic_search("gaming");
+    let summaries = engine.basic_search_summaries("gaming");
+    assert_eq!(summaries.len(), results.len());
+    for (summary, result) in summaries.iter().zip(&results) {
+        assert_eq!(*summary, result.product.summary());
+    }
+
+    let filters = SearchFilters::new().price_range(0.0, 100.0);
+    let filtered = engine.search_with_filters_summaries(Some("gaming"), &filters);
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].id, 2);
+}
+
+#[test]
+fn test_search_fields_name_only() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Wireless Headset", "HyperX", Category::Electronics, 90.0, 4.4));
+    let mut tagged_only = create_test_product(2, "Mechanical Keyboard", "Corsair", Category::Electronics, 120.0, 4.6);
+    tagged_only.add_tag("wireless".to_string());
+    engine.add_product(tagged_only);
+
+    let all_fields = engine.search_fields("wireless", SearchFields::all());
+    assert_eq!(all_fields.len(), 2);
+
+    let name_only = engine.search_fields("wireless", SearchFields::none().with_name());
+    assert_eq!(name_only.len(), 1);
+    assert_eq!(name_only[0].product.id, 1);
+
+    let tags_only = engine.search_fields("wireless", SearchFields::none().with_tags());
+    assert_eq!(tags_only.len(), 1);
+    assert_eq!(tags_only[0].product.id, 2);
+}
+
+#[test]
+fn test_search_filters_any_category() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Running Shoes", "Nike", Category::Sports, 120.0, 4.5));
+    engine.add_product(create_test_product(2, "Running Shorts", "Nike", Category::Clothing, 35.0, 4.2));
+    engine.add_product(create_test_product(3, "Running Book", "Penguin", Category::Books, 20.0, 4.0));
+
+    let filters = SearchFilters::new().categories(vec![Category::Sports, Category::Clothing]);
+
+    let mut ids: Vec<u64> = engine
+        .search_with_filters(Some("running"), &filters)
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 2]);
+
+    assert_eq!(engine.search_with_filters(None, &filters).len(), 2);
+    assert_eq!(SearchFilters::from_query_string(&filters.to_query_string()).unwrap(), filters);
+}
+
+#[test]
+fn test_out_of_stock_demotion() {
+    let build_engine = |scoring: ScoringConfig| {
+        let mut engine = SearchEngine::new().with_scoring(scoring);
+
+        let mut sold_out = create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 5.0);
+        sold_out.stock = 0;
+        engine.add_product(sold_out);
+        engine.add_product(create_test_product(2, "Gaming Laptop", "Dell", Category::Electronics, 900.0, 4.0));
+        engine
+    };
+
+    let default_results = build_engine(ScoringConfig::default()).basic_search("gaming laptop");
+    assert_eq!(default_results[0].product.id, 1);
+
+    let demoted = build_engine(ScoringConfig::new().out_of_stock_factor(0.5)).basic_search("gaming laptop");
+    assert_eq!(demoted.len(), 2);
+    assert_eq!(demoted[0].product.id, 2);
+    assert!((demoted[1].score - default_results[0].score * 0.5).abs() < 1e-9);
+
+    let engine = build_engine(ScoringConfig::new().out_of_stock_factor(0.5)).with_history(5);
+    let fields = engine.search_fields("gaming laptop", SearchFields::all());
+    assert_eq!(fields[0].product.id, 2);
+    assert_eq!(engine.recent_queries(), vec!["gaming laptop"]);
+}
+
+#[test]
+fn test_state_hash_covers_edges() {
+    let build = |reverse: bool| {
+        let mut engine = SearchEngine::new();
+        let mut ids = vec![1, 2, 3];
+        if reverse {
+            ids.reverse();
+        }
+        for id in ids {
+            engine.add_product(create_test_product(id, "Item", "Brand", Category::Electronics, 10.0, 4.0));
+        }
+        engine
+    };
+
+    let mut a = build(false);
+    let mut b = build(true);
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    a.add_product_relation(1, 2, 0.8, RelationType::Similar).unwrap();
+    assert_ne!(a.state_hash(), b.state_hash());
+
+    b.add_product_relation(2, 1, 0.8, RelationType::Similar).unwrap();
+    assert_eq!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn test_constrained_recommendations() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Summer Dress", "Zara", Category::Clothing, 80.0, 4.5));
+    engine.add_product(create_test_product(2, "Designer Handbag", "Gucci", Category::Clothing, 900.0, 4.8));
+    engine.add_product(create_test_product(3, "Straw Hat", "Zara", Category::Clothing, 25.0, 4.1));
+    engine.add_product(create_test_product(4, "Leather Sandals", "Birkenstock", Category::Clothing, 95.0, 4.4));
+    engine.add_product(create_test_product(5, "Sunscreen", "Nivea", Category::Beauty, 15.0, 4.6));
+    engine.add_product(create_test_product(6, "Silk Scarf", "Hermes", Category::Clothing, 400.0, 4.7));
+
+    engine.add_product_relation(1, 2, 0.95, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 6, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 5, 0.85, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.6, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 4, 0.5, RelationType::Similar).unwrap();
+
+    let filters = SearchFilters::new()
+        .price_range(0.0, 100.0)
+        .category(Category::Clothing);
+
+    let results = engine.constrained_recommendations(1, &filters, 2);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 4]);
+
+    let all = engine.constrained_recommendations(1, &filters, 10);
+    assert_eq!(all.len(), 2);
+}
+
+#[test]
+fn test_recommend_from_filters() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Phone", "Apple", Category::Electronics, 900.0, 4.6));
+    engine.add_product(create_test_product(2, "Laptop", "Apple", Category::Electronics, 1500.0, 4.7));
+    engine.add_product(create_test_product(3, "Phone Case", "Spigen", Category::Electronics, 20.0, 4.3));
+    engine.add_product(create_test_product(4, "Laptop Sleeve", "Targus", Category::Electronics, 30.0, 4.1));
+    engine.add_product(create_test_product(5, "Galaxy", "Samsung", Category::Electronics, 800.0, 4.4));
+    engine.add_product(create_test_product(6, "Galaxy Charger", "Anker", Category::Electronics, 25.0, 4.2));
+
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(2, 4, 0.8, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 2, 0.7, RelationType::SameBrand).unwrap();
+    engine.add_product_relation(5, 6, 0.9, RelationType::BoughtTogether).unwrap();
+
+    let filters = SearchFilters::new().brand("Apple".to_string());
+    let results = engine.recommend_from_filters(&filters, 10);
+
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 4]);
+    assert!(results.iter().all(|r| r.product.brand != "Apple"));
+}
+
+#[test]
+fn test_recommend_from_filters_limit_skips_hidden_products() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Phone", "Apple", Category::Electronics, 900.0, 4.6));
+    engine.add_product(create_test_product(2, "Phone Case", "Spigen", Category::Electronics, 20.0, 4.3));
+    engine.add_product(create_test_product(3, "Charger", "Anker", Category::Electronics, 25.0, 4.2));
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.5, RelationType::BoughtTogether).unwrap();
+    engine.suppress_from_recommendations(2);
+
+    let filters = SearchFilters::new().brand("Apple".to_string());
+    let ids: Vec<u64> = engine.recommend_from_filters(&filters, 1).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3]);
+}
+
+#[test]
+fn test_search_related_to() {
+    let mut engine = SearchEngine::new();
+
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 60.0, 4.3));
+    engine.add_product(create_test_product(3, "Gaming Headset", "HyperX", Category::Electronics, 90.0, 4.4));
+    engine.add_product(create_test_product(4, "Gaming Chair", "Secretlab", Category::HomeDecor, 400.0, 4.6));
+    engine.add_product(create_test_product(5, "Gaming Desk", "Ikea", Category::HomeDecor, 250.0, 4.0));
+    engine.add_product(create_test_product(6, "Mouse Pad", "Razer", Category::Electronics, 20.0, 4.2));
+
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 6, 0.7, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(6, 3, 0.5, RelationType::Similar).unwrap();
+    engine.add_product_relation(4, 5, 0.8, RelationType::BoughtTogether).unwrap();
+
+    assert_eq!(engine.basic_search("gaming").len(), 5);
+
+    let mut ids: Vec<u64> = engine
+        .search_related_to("gaming", 1, 2)
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec![2, 3]);
+
+    assert_eq!(engine.search_related_to("gaming", 1, 1).len(), 1);
+}
+
+#[test]
+fn test_search_history() {
+    let mut engine = SearchEngine::new().with_history(3);
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+
+    engine.basic_search("laptop");
+    engine.basic_search("mouse");
+    engine.search_with_filters(Some("keyboard"), &SearchFilters::new());
+    engine.search_with_filters(None, &SearchFilters::new());
+    engine.basic_search("laptop");
+
+    assert_eq!(engine.recent_queries(), vec!["laptop", "keyboard", "mouse"]);
+
+    engine.basic_search("monitor");
+    assert_eq!(engine.recent_queries(), vec!["monitor", "laptop", "keyboard"]);
+
+    let no_history = SearchEngine::new();
+    no_history.basic_search("laptop");
+    assert!(no_history.recent_queries().is_empty());
+}
+
+#[test]
+fn test_related_tags() {
+    let mut engine = SearchEngine::new();
+
+    let mut keyboard = create_test_product(1, "Mechanical Keyboard", "Corsair", Category::Electronics, 150.0, 4.5);
+    keyboard.add_tag("gaming".to_string());
+    keyboard.add_tag("rgb".to_string());
+    let mut mouse = create_test_product(2, "Gaming Mouse", "Logitech", Category::Electronics, 80.0, 4.4);
+    mouse.add_tag("gaming".to_string());
+    mouse.add_tag("rgb".to_string());
+    mouse.add_tag("wireless".to_string());
+    let mut headset = create_test_product(3, "Headset", "HyperX", Category::Electronics, 120.0, 4.2);
+    headset.add_tag("wireless".to_string());
+    headset.add_tag("audio".to_string());
+
+    engine.add_product(keyboard);
+    engine.add_product(mouse);
+    engine.add_product(headset);
+
+    let related = engine.related_tags("gaming", 5);
+    assert_eq!(related[0].0, "rgb");
+    assert!((related[0].1 - 1.0).abs() < 1e-9);
+    assert_eq!(related.len(), 2);
+    assert_eq!(related[1].0, "wireless");
+
+    assert_eq!(engine.related_tags("Gaming", 1).len(), 1);
+    assert!(engine.related_tags("unknown", 5).is_empty());
+}
+
+#[test]
+fn test_deactivated_product_hidden_from_search() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Office Laptop", "Dell", Category::Electronics, 800.0, 4.0));
+
+    assert!(engine.deactivate_product(1));
+    assert!(!engine.deactivate_product(99));
+    assert!(!engine.get_product(1).unwrap().active);
+
+    let ids: Vec<u64> = engine.basic_search("laptop").iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2]);
+    assert_eq!(engine.search_by_category(&Category::Electronics).len(), 1);
+    assert_eq!(engine.search_with_filters(Some("laptop"), &SearchFilters::new()).len(), 1);
+
+    let with_inactive = SearchFilters::new().include_inactive();
+    assert_eq!(engine.search_with_filters(Some("laptop"), &with_inactive).len(), 2);
+    assert_eq!(SearchFilters::from_query_string(&with_inactive.to_query_string()).unwrap(), with_inactive);
+}
+
+#[test]
+fn test_deactivated_product_edges_still_recommend() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop Bag", "Targus", Category::Electronics, 50.0, 4.0));
+    engine.add_product(create_test_product(3, "Bag Strap", "Targus", Category::Electronics, 15.0, 4.0));
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(2, 3, 0.8, RelationType::BoughtTogether).unwrap();
+
+    engine.deactivate_product(2);
+
+    let direct: Vec<u64> = engine.get_recommendations_for_product(1, 5).iter().map(|r| r.product.id).collect();
+    assert!(direct.is_empty());
+
+    let second_degree: Vec<u64> = engine
+        .get_second_degree_recommendations(1, 5)
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    assert_eq!(second_degree, vec![3]);
+    assert_eq!(engine.get_graph_stats(), (3, 2));
+}
+
+#[test]
+fn test_search_corrected() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Notebook Pro", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Wireless Mouse", "Logitech", Category::Electronics, 50.0, 4.2));
+    engine.add_product(create_test_product(3, "Running Shoes", "Nike", Category::Sports, 200.0, 4.0));
+
+    let (results, corrected) = engine.search_corrected("notbook mouze");
+    assert_eq!(corrected.as_deref(), Some("notebook mouse"));
+    let mut ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 2]);
+
+    let (results, corrected) = engine.search_corrected("notebook");
+    assert_eq!(corrected, None);
+    assert_eq!(results.len(), 1);
+
+    let (results, corrected) = engine.search_corrected("xyzzy");
+    assert_eq!(corrected, None);
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_hybrid_search_with_custom_combine() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Office Laptop", "Dell", Category::Electronics, 800.0, 1.0));
+    engine.add_product(create_test_product(3, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0));
+    engine.add_product_relation(1, 3, 1.0, RelationType::BoughtTogether).unwrap();
+
+    let filters = SearchFilters::new();
+    let position = |results: &[SearchResult]| results.iter().position(|r| r.product.id == 3).unwrap();
+
+    let default_results = engine.hybrid_search(Some("laptop"), &filters, true);
+    let weighted_results = engine.hybrid_search_with(Some("laptop"), &filters, |base, rec| {
+        base * 0.9 + rec as f64 * 0.9
+    });
+
+    assert_eq!(default_results[position(&default_results)].match_type, MatchType::Recommendation);
+    assert!(position(&weighted_results) < position(&default_results));
+    assert_eq!(default_results.len(), weighted_results.len());
+}
+
+#[test]
+fn test_suggest_and_merge_tags() {
+    let mut engine = SearchEngine::new();
+
+    let mut first = create_test_product(1, "Notebook Pro", "Dell", Category::Electronics, 1500.0, 4.5);
+    first.add_tag("laptop".to_string());
+    let mut second = create_test_product(2, "Ultrabook", "Asus", Category::Electronics, 1200.0, 4.3);
+    second.add_tag("laptop".to_string());
+    second.add_tag("portable".to_string());
+    let mut third = create_test_product(3, "Gaming Rig", "MSI", Category::Electronics, 2000.0, 4.7);
+    third.add_tag("laptops".to_string());
+    third.add_tag("gaming".to_string());
+
+    engine.add_product(first);
+    engine.add_product(second);
+    engine.add_product(third);
+
+    let merges = engine.suggest_tag_merges(1);
+    assert_eq!(merges, vec![("laptops".to_string(), "laptop".to_string())]);
+
+    assert_eq!(engine.merge_tags("laptops", "laptop"), 1);
+    assert_eq!(engine.get_product(3).unwrap().tags, vec!["gaming".to_string(), "laptop".to_string()]);
+
+    let filters = SearchFilters::new().add_tag("laptop".to_string());
+    let mut ids: Vec<u64> = engine.search_with_filters(None, &filters).iter().map(|r| r.product.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert!(engine.suggest_tag_merges(1).is_empty());
+}
+
+#[test]
+fn test_recommendations_scored_as_percentages() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop Bag", "Targus", Category::Electronics, 50.0, 4.0));
+    engine.add_product(create_test_product(3, "Mouse", "Logitech", Category::Electronics, 30.0, 4.0));
+    engine.add_product(create_test_product(4, "Sticker", "Generic", Category::Other("Misc".to_string()), 2.0, 3.0));
+    engine.add_product_relation(1, 2, 1.0, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.55, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 4, 0.1, RelationType::BoughtTogether).unwrap();
+
+    let scored: Vec<(u64, u8)> = engine
+        .get_recommendations_scored(1, 10)
+        .iter()
+        .map(|(result, percent)| (result.product.id, *percent))
+        .collect();
+    assert_eq!(scored, vec![(2, 100), (3, 50), (4, 0)]);
+
+    let single = engine.get_recommendations_scored(1, 1);
+    assert_eq!(single.len(), 1);
+    assert_eq!(single[0].1, 100);
+    assert!(engine.get_recommendations_scored(99, 5).is_empty());
+}
+
+#[test]
+fn test_filter_by_stock_range() {
+    let mut engine = SearchEngine::new();
+
+    let mut bulk = create_test_product(1, "Cable Pack", "Generic", Category::Electronics, 20.0, 4.0);
+    bulk.stock = 50;
+    let mut short = create_test_product(2, "Cable Reel", "Generic", Category::Electronics, 40.0, 4.0);
+    short.stock = 8;
+    let mut clearance = create_test_product(3, "Cable Tester", "Generic", Category::Electronics, 60.0, 4.0);
+    clearance.stock = 3;
+
+    engine.add_product(bulk);
+    engine.add_product(short);
+    engine.add_product(clearance);
+
+    let ids = |filters: &SearchFilters| {
+        let mut ids: Vec<u64> = engine.search_with_filters(None, filters).iter().map(|r| r.product.id).collect();
+        ids.sort();
+        ids
+    };
+
+    assert_eq!(ids(&SearchFilters::new().min_stock(10)), vec![1]);
+    assert_eq!(ids(&SearchFilters::new().max_stock(5)), vec![3]);
+    assert_eq!(ids(&SearchFilters::new().min_stock(5).max_stock(10)), vec![2]);
+
+    let filters = SearchFilters::new().min_stock(5).max_stock(10);
+    assert_eq!(filters.to_query_string(), "stock=5-10");
+    assert_eq!(SearchFilters::from_query_string("stock=10-").unwrap(), SearchFilters::new().min_stock(10));
+}
+
+#[test]
+fn test_whole_word_match_ranks_above_substring() {
+    let mut engine = SearchEngine::new();
+    let mut professional = create_test_product(1, "Professional Laptop", "Dell", Category::Electronics, 1500.0, 4.5);
+    professional.add_tag("productivity".to_string());
+    let mut pro = create_test_product(2, "Laptop Pro", "Asus", Category::Electronics, 1500.0, 4.5);
+    pro.add_tag("productivity".to_string());
+    engine.add_product(professional);
+    engine.add_product(pro);
+
+    let ids: Vec<u64> = engine
+        .search_with_filters(Some("pro"), &SearchFilters::new())
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    assert_eq!(ids, vec![2, 1]);
+
+    let whole_word = engine.get_product(2).unwrap().search_score("pro");
+    let substring = engine.get_product(1).unwrap().search_score("pro");
+    assert!(whole_word > substring);
+}
+
+#[test]
+fn test_upsert_product() {
+    let mut engine = SearchEngine::new();
+
+    let inserted = engine.upsert_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    assert!(inserted.is_none());
+    assert_eq!(engine.get_product_count(), 1);
+    assert_eq!(engine.get_graph_stats(), (1, 0));
+    assert_eq!(engine.basic_search("gaming").len(), 1);
+
+    let replaced = engine.upsert_product(create_test_product(1, "Office Desktop", "Dell", Category::Electronics, 900.0, 4.0));
+    assert_eq!(replaced.unwrap().name, "Gaming Laptop");
+    assert_eq!(engine.get_product_count(), 1);
+    assert_eq!(engine.get_graph_stats(), (1, 0));
+
+    assert!(engine.basic_search("gaming").is_empty());
+    assert!(engine.search_by_brand("Asus").is_empty());
+    assert_eq!(engine.basic_search("desktop")[0].product.id, 1);
+    assert_eq!(engine.search_by_brand("Dell").len(), 1);
+}
+
+#[test]
+fn test_upsert_product_updates_graph_category() {
+    let mut engine = SearchEngine::new();
+    engine.upsert_product(create_test_product(1, "Desk Lamp", "Ikea", Category::Electronics, 40.0, 4.0));
+    assert_eq!(engine.graph().get_product_node(1).unwrap().category, "Electronics");
+
+    engine.upsert_product(create_test_product(1, "Desk Lamp", "Ikea", Category::HomeDecor, 40.0, 4.0));
+    assert_eq!(engine.graph().get_product_node(1).unwrap().category, "Home & Decor");
+    assert_eq!(engine.get_graph_stats(), (1, 0));
+}
+
+#[test]
+fn test_on_sale_filter_and_deals() {
+    let mut engine = SearchEngine::new();
+
+    let mut small_discount = create_test_product(1, "Headphones", "Sony", Category::Electronics, 200.0, 4.5);
+    small_discount.sale_price = Some(180.0);
+    let mut big_discount = create_test_product(2, "Speaker", "JBL", Category::Electronics, 100.0, 4.0);
+    big_discount.sale_price = Some(60.0);
+    let mut not_a_discount = create_test_product(3, "Microphone", "Shure", Category::Electronics, 150.0, 4.2);
+    not_a_discount.sale_price = Some(150.0);
+    let full_price = create_test_product(4, "Turntable", "Audio-Technica", Category::Electronics, 300.0, 4.8);
+
+    engine.add_product(small_discount);
+    engine.add_product(big_discount);
+    engine.add_product(not_a_discount);
+    engine.add_product(full_price);
+
+    let filters = SearchFilters::new().on_sale_only();
+    let mut ids: Vec<u64> = engine.search_with_filters(None, &filters).iter().map(|r| r.product.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(SearchFilters::from_query_string(&filters.to_query_string()).unwrap(), filters);
+
+    let deals = engine.deals(10);
+    let ids: Vec<u64> = deals.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2, 1]);
+    assert!((deals[0].score - 40.0).abs() < 1e-9);
+    assert!((deals[1].score - 10.0).abs() < 1e-9);
+    assert_eq!(engine.deals(1).len(), 1);
+}
+
+#[test]
+fn test_recommendations_quality_weighted() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Cheap Mouse", "Generic", Category::Electronics, 10.0, 1.5));
+    engine.add_product(create_test_product(3, "Good Mouse", "Logitech", Category::Electronics, 50.0, 4.8));
+    engine.add_product_relation(1, 2, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+
+    let results = engine.get_recommendations_quality_weighted(1, 5);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 2]);
+
+    let raw = 0.9 * 1.5;
+    assert!((results[0].score - raw * (0.5 + 0.48)).abs() < 1e-6);
+    assert!((results[1].score - raw * (0.5 + 0.15)).abs() < 1e-6);
+    assert_eq!(engine.get_recommendations_quality_weighted(1, 1).len(), 1);
+}
+
+#[test]
+fn test_brand_idf_scoring() {
+    let build = |scoring: ScoringConfig| {
+        let mut engine = SearchEngine::new().with_scoring(scoring);
+        for id in 1..=50 {
+            engine.add_product(create_test_product(id, "Basic Tee", "Generic", Category::Clothing, 20.0, 4.0));
+        }
+        engine.add_product(create_test_product(51, "Linen Tee", "Artisan", Category::Clothing, 90.0, 4.0));
+        engine
+    };
+    let brand_score = |engine: &SearchEngine, brand: &str| {
+        engine.search_fields(brand, SearchFields::none().with_brand())[0].score
+    };
+
+    let plain = build(ScoringConfig::new());
+    assert_eq!(brand_score(&plain, "artisan"), brand_score(&plain, "generic"));
+
+    let idf = build(ScoringConfig::new().brand_idf(true));
+    assert!(brand_score(&idf, "artisan") > brand_score(&idf, "generic"));
+    assert!((brand_score(&idf, "artisan") - 5.0 * (51f64.ln() + 1.0) * 1.4).abs() < 1e-9);
+}
+
+#[test]
+fn test_set_active_by_filter() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Winter Jacket", "North", Category::Clothing, 300.0, 4.5));
+    engine.add_product(create_test_product(2, "Wool Scarf", "North", Category::Clothing, 40.0, 4.0));
+    engine.add_product(create_test_product(3, "Space Heater", "Philips", Category::Electronics, 120.0, 4.2));
+
+    let clothing = SearchFilters::new().category(Category::Clothing);
+    assert_eq!(engine.set_active_by_filter(&clothing, false), 2);
+    assert_eq!(engine.set_active_by_filter(&clothing, false), 0);
+
+    assert!(engine.search_by_category(&Category::Clothing).is_empty());
+    assert!(engine.basic_search("jacket").is_empty());
+    let ids: Vec<u64> = engine.search_with_filters(None, &SearchFilters::new()).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3]);
+
+    assert_eq!(engine.set_active_by_filter(&clothing, true), 2);
+    assert_eq!(engine.search_by_category(&Category::Clothing).len(), 2);
+}
+
+#[test]
+fn test_normalized_scores_comparable_across_methods() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.8));
+    engine.add_product(create_test_product(2, "Budget Laptop", "Acer", Category::Electronics, 500.0, 3.0));
+
+    let text = engine.basic_search("laptop");
+    let category = engine.search_by_category(&Category::Electronics);
+    assert!(text[0].score > 10.0 * category[0].score);
+
+    let text_scale = ScoreScale::from_results(&text);
+    let category_scale = ScoreScale::from_results(&category);
+
+    for id in [1, 2] {
+        let from_text = text.iter().find(|r| r.product.id == id).unwrap().normalized_score(text_scale);
+        let from_category = category.iter().find(|r| r.product.id == id).unwrap().normalized_score(category_scale);
+        assert_eq!(from_text, from_category);
+    }
+
+    assert_eq!(text[0].normalized_score(text_scale), 1.0);
+    assert_eq!(text[1].normalized_score(text_scale), 0.0);
+    assert_eq!(ScoreScale::new(2.0, 2.0).normalize(2.0), 1.0);
+    assert_eq!(ScoreScale::new(0.0, 10.0).normalize(15.0), 1.0);
+}
+
+#[test]
+fn test_query_builder_matches_manual_steps() {
+    let mut engine = SearchEngine::new();
+    for (id, name, price, rating) in [
+        (1, "Gaming Laptop", 1500.0, 4.7),
+        (2, "Office Laptop", 700.0, 4.1),
+        (3, "Budget Laptop", 400.0, 3.6),
+        (4, "Laptop Stand", 60.0, 4.4),
+        (5, "Ultra Laptop", 2200.0, 4.9),
+    ] {
+        engine.add_product(create_test_product(id, name, "Brand", Category::Electronics, price, rating));
+    }
+
+    let filters = SearchFilters::new().price_range(100.0, 2000.0);
+    let page = engine
+        .query()
+        .text("laptop")
+        .filter(filters.clone())
+        .sort(SortBy::PriceAsc)
+        .page(1, 2)
+        .execute();
+
+    let mut manual = engine.search_with_filters(Some("laptop"), &filters);
+    sort_results(&mut manual, &SortBy::PriceAsc);
+    let expected: Vec<u64> = manual.iter().skip(1).take(2).map(|r| r.product.id).collect();
+
+    let ids: Vec<u64> = page.results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, expected);
+    assert_eq!(ids, vec![2, 1]);
+    assert_eq!(page.total, 3);
+    assert_eq!((page.offset, page.limit), (1, 2));
+
+    let everything = engine.query().sort(SortBy::RatingDesc).execute();
+    assert_eq!(everything.total, 5);
+    assert_eq!(everything.results[0].product.id, 5);
+}
+
+#[test]
+fn test_relation_to_unindexed_product_rejected() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+
+    let error = engine.add_product_relation(1, 42, 0.8, RelationType::BoughtTogether).unwrap_err();
+    assert_eq!(error, SearchError::UnknownProduct(42));
+    assert_eq!(error.to_string(), "product 42 is not in the index");
+
+    assert_eq!(
+        engine.add_product_relation(7, 1, 0.8, RelationType::Similar),
+        Err(SearchError::UnknownProduct(7))
+    );
+    assert_eq!(engine.get_graph_stats(), (1, 0));
+}
+
+#[test]
+fn test_tag_facets() {
+    let mut engine = SearchEngine::new();
+    let catalog = [
+        (1, "Gaming Laptop", vec!["gaming", "rgb", "portable"]),
+        (2, "Gaming Mouse", vec!["gaming", "rgb", "wireless"]),
+        (3, "Gaming Headset", vec!["gaming", "wireless", "audio"]),
+        (4, "Gaming Chair", vec!["gaming", "ergonomic"]),
+        (5, "Office Chair", vec!["ergonomic", "office"]),
+    ];
+    for (id, name, tags) in catalog {
+        let mut product = create_test_product(id, name, "Brand", Category::Electronics, 100.0, 4.0);
+        for tag in tags {
+            product.add_tag(tag.to_string());
+        }
+        engine.add_product(product);
+    }
+
+    let facets = engine.tag_facets("gaming", 3);
+    assert_eq!(
+        facets,
+        vec![
+            ("rgb".to_string(), 2),
+            ("wireless".to_string(), 2),
+            ("audio".to_string(), 1),
+        ]
+    );
+    assert!(engine.tag_facets("gaming", 10).iter().all(|(tag, _)| tag != "gaming" && tag != "office"));
+    assert!(engine.tag_facets("nothing", 5).is_empty());
+}
+
+#[test]
+fn test_export_catalog_json_round_trip() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Asus", Category::Electronics, 1200.0, 4.5));
+    engine.add_product(create_test_product(2, "Running Shoes", "Nike", Category::Sports, 300.0, 4.2));
+
+    let mut buffer = Vec::new();
+    engine.export_catalog_json(&mut buffer).unwrap();
+
+    let mut restored = SearchEngine::new();
+    assert_eq!(restored.import_catalog_json(buffer.as_slice()).unwrap(), 2);
+    assert_eq!(restored.state_hash(), engine.state_hash());
+    assert_eq!(restored.get_graph_stats(), (2, 0));
+    assert_eq!(restored.basic_search("laptop")[0].product.id, 1);
+}
+
+#[test]
+fn test_engine_also_viewed() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Tablet", "Apple", Category::Electronics, 800.0, 4.7));
+    engine.add_product(create_test_product(3, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0));
+    engine.add_product_relation(1, 2, 0.6, RelationType::AlsoViewed).unwrap();
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+
+    let ids: Vec<u64> = engine.get_also_viewed(1).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2]);
+
+    let recommended: Vec<u64> = engine.get_recommendations_for_product(1, 5).iter().map(|r| r.product.id).collect();
+    assert_eq!(recommended, vec![3, 2]);
+}
+
+#[test]
+fn test_audit_relations() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "XPS Laptop", "Dell", Category::Electronics, 1500.0, 4.6));
+    engine.add_product(create_test_product(2, "Zenbook", "Asus", Category::Electronics, 1300.0, 4.4));
+    engine.add_product(create_test_product(3, "Inspiron", "Dell", Category::Electronics, 900.0, 4.1));
+    engine.add_product(create_test_product(4, "Running Shoes", "Nike", Category::Sports, 300.0, 4.2));
+
+    engine.add_product_relation(1, 2, 0.6, RelationType::SameBrand).unwrap();
+    engine.add_product_relation(1, 3, 0.6, RelationType::SameBrand).unwrap();
+    engine.add_product_relation(2, 4, 0.7, RelationType::Similar).unwrap();
+    engine.add_product_relation(1, 4, 0.3, RelationType::BoughtTogether).unwrap();
+
+    let issues = engine.audit_relations();
+    assert_eq!(issues.len(), 2);
+    assert!(issues.contains(&RelationIssue::BrandMismatch { product_id_1: 1, product_id_2: 2 }));
+    assert!(issues.contains(&RelationIssue::SimilarAcrossCategories { product_id_1: 2, product_id_2: 4 }));
+}
+
+#[test]
+fn test_tag_filter_matches_index_semantics() {
+    let mut engine = SearchEngine::new();
+    let mut keyboard = create_test_product(1, "Mechanical Keyboard", "Corsair", Category::Electronics, 150.0, 4.5);
+    keyboard.add_tag("Gaming".to_string());
+    let mut chair = create_test_product(2, "Office Chair", "Herman", Category::HomeDecor, 900.0, 4.8);
+    chair.add_tag("endgame".to_string());
+    engine.add_product(keyboard);
+    engine.add_product(chair);
+
+    let ids = |tag: &str| {
+        let mut ids: Vec<u64> = engine
+            .search_with_filters(None, &SearchFilters::new().add_tag(tag.to_string()))
+            .iter()
+            .map(|r| r.product.id)
+            .collect();
+        ids.sort();
+        ids
+    };
+
+    // "game" used to match both products as a substring while the tag index found neither
+    assert!(ids("game").is_empty());
+    assert_eq!(ids("gaming"), vec![1]);
+    assert_eq!(ids("GAMING"), vec![1]);
+    assert_eq!(ids("endgame"), vec![2]);
+}
+
+#[test]
+fn test_recommendations_excluding_owned_items() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Camera", "Canon", Category::Electronics, 2500.0, 4.7));
+    for (id, name, weight) in [(2, "Lens", 0.9), (3, "Tripod", 0.8), (4, "Memory Card", 0.7), (5, "Camera Bag", 0.6)] {
+        engine.add_product(create_test_product(id, name, "Generic", Category::Electronics, 100.0, 4.0));
+        engine.add_product_relation(1, id, weight, RelationType::BoughtTogether).unwrap();
+    }
+
+    let owned: HashSet<u64> = [2, 3].into_iter().collect();
+    let ids: Vec<u64> = engine
+        .get_recommendations_excluding(1, &owned, 2)
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    assert_eq!(ids, vec![4, 5]);
+
+    assert_eq!(engine.get_recommendations_excluding(1, &HashSet::new(), 2)[0].product.id, 2);
+    assert_eq!(engine.get_recommendations_excluding(1, &owned, 10).len(), 2);
+}
+
+#[test]
+fn test_subsystem_accessors() {
+    let mut engine = SearchEngine::new();
+    let mut laptop = create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5);
+    laptop.add_tag("portable".to_string());
+    let mut mouse = create_test_product(2, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0);
+    mouse.add_tag("wireless".to_string());
+    engine.add_product(laptop);
+    engine.add_product(mouse);
+    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether).unwrap();
+
+    let connections = engine.graph().get_connections(1);
+    assert_eq!(connections, vec![(2, 0.8, RelationType::BoughtTogether)]);
+    assert_eq!(engine.index().all_tags(), vec!["portable".to_string(), "wireless".to_string()]);
+    assert_eq!(engine.index().verify_consistency(), Ok(()));
+}
+
+#[test]
+fn test_fuzzy_search_prefers_closer_matches() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Rotbrook Speaker", "Sony", Category::Electronics, 300.0, 5.0));
+    engine.add_product(create_test_product(2, "Notebook Pro", "Dell", Category::Electronics, 1500.0, 3.0));
+    engine.add_product(create_test_product(3, "Desk Lamp", "Philips", Category::HomeDecor, 80.0, 4.0));
+
+    let results = engine.fuzzy_search("notbook", 2);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2, 1]);
+
+    let notebook = engine.get_product(2).unwrap();
+    assert!(notebook.fuzzy_search_score("notebook", 0) > notebook.fuzzy_search_score("notbook", 1));
+    assert_eq!(engine.fuzzy_search("notbook", 1).len(), 1);
+}
+
+#[test]
+fn test_price_filter_with_quantity_context() {
+    let mut engine = SearchEngine::new();
+    let mut paper = create_test_product(1, "Printer Paper", "Chamex", Category::Other("Office".to_string()), 30.0, 4.0);
+    paper.price_tiers = vec![(10, 27.0), (100, 21.0)];
+    engine.add_product(paper);
+
+    let budget = SearchFilters::new().price_range(0.0, 25.0);
+    assert!(engine.search_with_filters(None, &budget).is_empty());
+    assert!(engine.search_with_filters(None, &budget.clone().quantity(10)).is_empty());
+    assert_eq!(engine.search_with_filters(None, &budget.clone().quantity(100)).len(), 1);
+
+    let filters = budget.quantity(100);
+    assert_eq!(SearchFilters::from_query_string(&filters.to_query_string()).unwrap(), filters);
+}
+
+#[test]
+fn test_try_get_recommendations_for_product() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 50.0, 4.0));
+    engine.add_product(create_test_product(3, "Desk Lamp", "Philips", Category::HomeDecor, 80.0, 4.0));
+    engine.add_product_relation(1, 2, 0.8, RelationType::BoughtTogether).unwrap();
+
+    assert_eq!(engine.try_get_recommendations_for_product(1, 5).unwrap().len(), 1);
+    assert!(engine.try_get_recommendations_for_product(3, 5).unwrap().is_empty());
+    assert_eq!(
+        engine.try_get_recommendations_for_product(99, 5).unwrap_err(),
+        SearchError::UnknownProduct(99)
+    );
+}
+
+#[test]
+fn test_limited_similar_and_bought_together() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    for (id, rating) in [(2, 3.5), (3, 4.9), (4, 4.2), (5, 2.0)] {
+        engine.add_product(create_test_product(id, "Similar Laptop", "Brand", Category::Electronics, 900.0, rating));
+        engine.add_product_relation(1, id, 0.8, RelationType::Similar).unwrap();
+    }
+    for (id, rating) in [(6, 4.0), (7, 4.8)] {
+        engine.add_product(create_test_product(id, "Accessory", "Brand", Category::Electronics, 30.0, rating));
+        engine.add_product_relation(1, id, 0.8, RelationType::BoughtTogether).unwrap();
+    }
+
+    let ids: Vec<u64> = engine.search_similar_products_limited(1, 2).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 4]);
+    assert_eq!(engine.search_similar_products(1).len(), 4);
+
+    let all = engine.get_frequently_bought_together(1);
+    assert_eq!(all.len(), 2);
+    assert!(all.iter().all(|r| r.score == r.product.rating as f64));
+    let limited = engine.get_frequently_bought_together_limited(1, 1);
+    assert_eq!(limited.len(), 1);
+    assert_eq!(limited[0].product.id, all[0].product.id);
+}
+
+#[test]
+fn test_compound_sort() {
+    let mut engine = SearchEngine::new();
+    for (id, price, rating) in [(1, 300.0, 4.5), (2, 100.0, 4.5), (3, 200.0, 4.9), (4, 100.0, 4.5), (5, 50.0, 3.0)] {
+        engine.add_product(create_test_product(id, "Headphones", "Brand", Category::Electronics, price, rating));
+    }
+
+    let page = engine
+        .query()
+        .sort(SortBy::Compound(vec![SortKey::RatingDesc, SortKey::PriceAsc]))
+        .execute();
+    let ids: Vec<u64> = page.results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 2, 4, 1, 5]);
+
+    let mut results = engine.search_with_filters(None, &SearchFilters::new());
+    sort_results(&mut results, &SortBy::Compound(vec![SortKey::PriceAsc]));
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![5, 2, 4, 3, 1]);
+}
+
+#[test]
+fn test_cart_recommendations_spread_across_categories() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Monitor", "LG", Category::Electronics, 400.0, 4.4));
+    let neighbors = [
+        (3, Category::Electronics, 0.9),
+        (4, Category::Electronics, 0.85),
+        (5, Category::Electronics, 0.8),
+        (6, Category::Books, 0.5),
+        (7, Category::HomeDecor, 0.45),
+    ];
+    for (id, category, weight) in neighbors {
+        engine.add_product(create_test_product(id, "Item", "Brand", category, 50.0, 4.0));
+        engine.add_product_relation(1, id, weight, RelationType::BoughtTogether).unwrap();
+        engine.add_product_relation(2, id, weight, RelationType::BoughtTogether).unwrap();
+    }
+    engine.add_product_relation(1, 2, 1.0, RelationType::BoughtTogether).unwrap();
+
+    let plain: Vec<u64> = engine
+        .graph()
+        .get_recommendations_multi(&[1, 2], 3)
+        .iter()
+        .map(|(id, _)| *id)
+        .collect();
+    assert_eq!(plain, vec![3, 4, 5]);
+
+    let results = engine.cart_recommendations(&[1, 2], 3);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![3, 6, 7]);
+
+    let categories: HashSet<Category> = results.iter().map(|r| r.product.category.clone()).collect();
+    assert_eq!(categories.len(), 3);
+    assert!(results.iter().all(|r| r.product.id != 1 && r.product.id != 2));
+}
+
+#[test]
+fn test_auto_link_similar_threshold() {
+    let mut engine = SearchEngine::new();
+    let tagged = |id: u64, tags: &[&str]| {
+        let mut product = create_test_product(id, "Item", "Brand", Category::Electronics, 100.0, 4.0);
+        for tag in tags {
+            product.add_tag(tag.to_string());
+        }
+        product
+    };
+
+    engine.add_product(tagged(1, &["gaming", "rgb", "wireless"]));
+    engine.add_product(tagged(2, &["gaming", "rgb", "wireless", "mouse"]));
+    engine.add_product(tagged(3, &["gaming", "office", "ergonomic", "mesh"]));
+
+    assert_eq!(engine.auto_link_similar(0.5), 1);
+    assert!(engine.graph().has_edge(1, 2));
+    assert!(!engine.graph().has_edge(1, 3));
+    assert!(!engine.graph().has_edge(2, 3));
+
+    let connections = engine.graph().get_connections(1);
+    assert_eq!(connections, vec![(2, 0.75, RelationType::Similar)]);
+
+    assert_eq!(engine.auto_link_similar(0.5), 0);
+    assert_eq!(engine.auto_link_similar(0.1), 2);
+}
+
+#[test]
+fn test_search_queries_are_trimmed() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming  Laptop", "  Dell ", Category::Electronics, 1500.0, 4.5));
+
+    let results = engine.basic_search("  gaming   laptop ");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].match_type, MatchType::ExactName);
+    assert!(results[0].score > 10.0);
+
+    assert_eq!(engine.search_by_brand(" DELL  ").len(), 1);
+    let filters = SearchFilters::new().brand("dell".to_string());
+    assert_eq!(engine.search_with_filters(None, &filters).len(), 1);
+}
+
+fn tshirt_with_variants() -> Product {
+    let mut tshirt = create_test_product(1, "Basic T-Shirt", "Hering", Category::Clothing, 50.0, 4.3);
+    tshirt.stock = 0;
+    for (id, size, stock) in [(11, "S", 0), (12, "M", 3), (13, "L", 0)] {
+        tshirt.variants.push(Variant {
+            id,
+            attributes: [("size".to_string(), size.to_string())].into_iter().collect(),
+            price_delta: 0.0,
+            stock,
+        });
+    }
+    tshirt
+}
+
+#[test]
+fn test_variant_stock_satisfies_in_stock_filter() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(tshirt_with_variants());
+    let mut sold_out = create_test_product(2, "Polo Shirt", "Hering", Category::Clothing, 80.0, 4.0);
+    sold_out.stock = 0;
+    engine.add_product(sold_out);
+
+    let ids: Vec<u64> = engine
+        .search_with_filters(None, &SearchFilters::new().in_stock_only())
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    assert_eq!(ids, vec![1]);
+
+    let results = engine.search_with_variants(Some("t-shirt"), &SearchFilters::new().in_stock_only());
+    assert_eq!(results.len(), 1);
+    let variant_ids: Vec<u64> = results[0].1.iter().map(|variant| variant.id).collect();
+    assert_eq!(variant_ids, vec![12]);
+}
+
+#[test]
+fn test_variant_level_stock_respected() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(tshirt_with_variants());
+
+    let small = SearchFilters::new()
+        .variant_attribute("size".to_string(), "S".to_string())
+        .in_stock_only();
+    assert!(engine.search_with_filters(None, &small).is_empty());
+
+    let medium = SearchFilters::new()
+        .variant_attribute("size".to_string(), "m".to_string())
+        .in_stock_only();
+    let results = engine.search_with_variants(None, &medium);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].1.len(), 1);
+    assert_eq!(results[0].1[0].id, 12);
+
+    let any_small = SearchFilters::new().variant_attribute("size".to_string(), "S".to_string());
+    assert_eq!(engine.search_with_variants(None, &any_small)[0].1[0].id, 11);
+    assert_eq!(SearchFilters::from_query_string(&medium.to_query_string()).unwrap(), medium);
+}
+
+#[test]
+fn test_search_overrides_pin_and_bury() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop Pro", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop", "HP", Category::Electronics, 900.0, 4.0));
+    engine.add_product(create_test_product(3, "Laptop Stand Laptop", "Acme", Category::Electronics, 40.0, 3.5));
+
+    let baseline: Vec<u64> = engine.basic_search("laptop").iter().map(|r| r.product.id).collect();
+    assert_eq!(baseline.len(), 3);
+    let lowest = *baseline.last().unwrap();
+    let buried = baseline[0];
+
+    engine.set_query_overrides("  Laptop ", SearchOverrides::new().pin(lowest).bury(buried));
+    let results = engine.basic_search("laptop");
+    assert_eq!(results[0].product.id, lowest);
+    assert!(results.iter().all(|r| r.product.id != buried));
+    assert_eq!(results.len(), 2);
+
+    // Other queries fall back to the (empty) global overrides.
+    assert_eq!(engine.basic_search("stand").len(), 1);
+
+    engine.clear_query_overrides("laptop");
+    let restored: Vec<u64> = engine.basic_search("laptop").iter().map(|r| r.product.id).collect();
+    assert_eq!(restored, baseline);
+}
+
+#[test]
+fn test_search_overrides_apply_to_every_text_path() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop Pro", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop", "HP", Category::Electronics, 900.0, 4.0));
+    engine.add_product(create_test_product(3, "Laptop Stand Laptop", "Acme", Category::Electronics, 40.0, 3.5));
+    engine.set_query_overrides("laptop", SearchOverrides::new().pin(3).bury(2));
+
+    let filters = SearchFilters::new();
+    let paths = [
+        engine.search_with_filters(Some("laptop"), &filters),
+        engine.hybrid_search(Some("laptop"), &filters, true),
+        engine.fuzzy_search("laptop", 1),
+        engine.search_fields("laptop", SearchFields::all()),
+    ];
+    for results in paths {
+        let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+        assert_eq!(ids, vec![3, 1]);
+    }
+
+    // Browsing without a query has nothing to override.
+    assert_eq!(engine.search_with_filters(None, &filters).len(), 3);
+}
+
+#[test]
+fn test_search_dedup_keeps_pinned_product_first() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop Pro", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop", "HP", Category::Electronics, 900.0, 4.0));
+    engine.add_product(create_test_product(3, "Laptop Stand", "Acme", Category::Electronics, 40.0, 2.0));
+    assert_ne!(engine.search_dedup("laptop")[0].product.id, 3);
+
+    engine.set_query_overrides("laptop", SearchOverrides::new().pin(3));
+    let results = engine.search_dedup("laptop");
+    assert_eq!(results[0].product.id, 3);
+    assert_eq!(results.len(), 3);
+}
+
+#[test]
+fn test_search_with_recommendations_keeps_pinned_product_first() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop Pro", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop", "HP", Category::Electronics, 900.0, 4.0));
+    engine.add_product(create_test_product(3, "Laptop Stand", "Acme", Category::Electronics, 40.0, 2.0));
+    engine.add_product(create_test_product(4, "Mouse", "Logitech", Category::Electronics, 30.0, 4.8));
+    engine.add_product_relation(3, 4, 1.0, RelationType::BoughtTogether).unwrap();
+    engine.set_query_overrides("laptop", SearchOverrides::new().pin(3));
+
+    let results = engine.search_with_recommendations("laptop", true, 6);
+    assert_eq!(results[0].product.id, 3);
+    assert!(results.iter().any(|r| r.product.id == 4 && r.match_type == MatchType::Recommendation));
+}
+
+#[test]
+fn test_case_sensitive_brand() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Phone", "Apple", Category::Electronics, 999.0, 4.8));
+    engine.add_product(create_test_product(2, "Cider", "apple", Category::Food, 5.0, 4.0));
+
+    let insensitive = SearchFilters::new().brand("APPLE".to_string());
+    assert_eq!(engine.search_with_filters(None, &insensitive).len(), 2);
+
+    let sensitive = SearchFilters::new().brand("APPLE".to_string()).case_sensitive_brand();
+    assert!(engine.search_with_filters(None, &sensitive).is_empty());
+
+    let exact = SearchFilters::new().brand("Apple".to_string()).case_sensitive_brand();
+    let ids: Vec<u64> = engine.search_with_filters(None, &exact).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![1]);
+    assert_eq!(SearchFilters::from_query_string(&exact.to_query_string()).unwrap(), exact);
+
+    assert_eq!(engine.search_by_brand("APPLE").len(), 2);
+    let engine = engine.with_case_sensitive_brand(true);
+    assert!(engine.search_by_brand("APPLE").is_empty());
+    let ids: Vec<u64> = engine.search_by_brand("apple").iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2]);
+}
+
+#[test]
+fn test_debug_scoring_components() {
+    let mut engine = SearchEngine::new();
+    let mut sold_out = create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.0);
+    sold_out.stock = 0;
+    engine.add_product(sold_out);
+
+    let results = engine.basic_search("laptop");
+    assert!(results[0].score_components.is_none());
+
+    let engine = engine
+        .with_scoring(ScoringConfig::new().out_of_stock_factor(0.5))
+        .with_debug_scoring(true);
+    let results = engine.basic_search("laptop");
+    let components: ScoreBreakdown = results[0].score_components.unwrap();
+
+    assert!((components.rating_multiplier - 1.4).abs() < 1e-9);
+    assert_eq!(components.stock_factor, 0.5);
+    assert_eq!(components.brand_weight, 1.0);
+    assert!((components.total() - results[0].score).abs() < 1e-9);
+
+    let filtered = engine.search_with_filters(Some("laptop"), &SearchFilters::new());
+    assert!(filtered[0].score_components.is_some());
+
+    let fuzzy = engine.fuzzy_search("laptpo", 2);
+    let components = fuzzy[0].score_components.unwrap();
+    assert!((components.total() - fuzzy[0].score).abs() < 1e-9);
+    assert!(engine.search_by_brand("Dell")[0].score_components.is_none());
+}
+
+#[test]
+fn test_nearest_by_attributes() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop A", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Laptop B", "HP", Category::Electronics, 1010.0, 4.4));
+    engine.add_product(create_test_product(3, "Laptop C", "Acer", Category::Electronics, 400.0, 3.0));
+    engine.add_product(create_test_product(4, "Cable", "Acme", Category::Electronics, 10.0, 4.5));
+    engine.add_product(create_test_product(5, "Jacket", "Zara", Category::Clothing, 1000.0, 4.5));
+
+    let results = engine.nearest_by_attributes(1, 10, 1.0, 1.0);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+
+    assert_eq!(ids[0], 2);
+    assert_eq!(ids.len(), 3);
+    assert!(!ids.contains(&5));
+    assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+    // With price ignored, the cable's matching rating puts it ahead of laptop C.
+    let rating_only: Vec<u64> = engine.nearest_by_attributes(1, 10, 0.0, 1.0).iter().map(|r| r.product.id).collect();
+    assert_eq!(rating_only[0], 4);
+    assert_eq!(engine.nearest_by_attributes(1, 1, 1.0, 1.0).len(), 1);
+    assert!(engine.nearest_by_attributes(999, 5, 1.0, 1.0).is_empty());
+}
+
+#[test]
+fn test_suppress_from_recommendations() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 30.0, 4.0));
+    engine.add_product(create_test_product(3, "Clearance Mouse", "Acme", Category::Electronics, 5.0, 3.0));
+    engine.add_product_relation(1, 2, 0.5, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+
+    assert!(engine.suppress_from_recommendations(3));
+    assert!(!engine.suppress_from_recommendations(3));
+
+    let ids: Vec<u64> = engine.get_recommendations_for_product(1, 10).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2]);
+    assert!(engine.get_frequently_bought_together(1).iter().all(|r| r.product.id != 3));
+    assert!(engine.basic_search("clearance").iter().any(|r| r.product.id == 3));
+
+    assert!(engine.unsuppress_from_recommendations(3));
+    assert_eq!(engine.get_recommendations_for_product(1, 10)[0].product.id, 3);
+}
+
+#[test]
+fn test_result_overlap_with_synonyms() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Gaming Laptop", "Dell", Category::Electronics, 1500.0, 4.5));
+    engine.add_product(create_test_product(2, "Business Laptop", "HP", Category::Electronics, 900.0, 4.0));
+    engine.add_product(create_test_product(3, "Slim Notebook", "Asus", Category::Electronics, 800.0, 4.2));
+    engine.add_product(create_test_product(4, "Running Shoes", "Nike", Category::Sports, 120.0, 4.6));
+
+    assert_eq!(engine.result_overlap("laptop", "notebook"), 0.0);
+
+    engine.add_synonym("Laptop", " notebook ");
+    assert_eq!(engine.basic_search("notebook").len(), 3);
+    assert_eq!(engine.result_overlap("laptop", "notebook"), 1.0);
+    assert_eq!(engine.result_overlap("laptop", "shoes"), 0.0);
+    assert_eq!(engine.result_overlap("nothing", "nowhere"), 0.0);
+}
+
+#[test]
+fn test_default_category() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Smart Watch", "Apple", Category::Electronics, 400.0, 4.5));
+    engine.add_product(create_test_product(2, "Leather Watch", "Fossil", Category::Clothing, 150.0, 4.2));
+    engine.add_product(create_test_product(3, "Watch Band", "Fossil", Category::Clothing, 30.0, 4.0));
+
+    let engine = engine.with_default_category(Category::Electronics);
+
+    let results = engine.basic_search("watch");
+    assert_eq!(results.len(), 1);
+    assert!(results.iter().all(|r| r.product.category == Category::Electronics));
+    assert!(engine.search_by_brand("Fossil").is_empty());
+    assert_eq!(engine.search_with_filters(Some("watch"), &SearchFilters::new()).len(), 1);
+
+    let clothing = SearchFilters::new().category(Category::Clothing);
+    assert_eq!(engine.search_with_filters(Some("watch"), &clothing).len(), 2);
+    assert_eq!(engine.search_by_category(&Category::Clothing).len(), 2);
+}
+
+#[test]
+fn test_default_category_applies_to_hybrid_recommendations() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Smart Watch", "Apple", Category::Electronics, 400.0, 4.5));
+    engine.add_product(create_test_product(2, "Leather Watch", "Fossil", Category::Clothing, 150.0, 4.2));
+    engine.add_product(create_test_product(3, "Watch Band", "Fossil", Category::Clothing, 30.0, 4.0));
+    engine.add_product_relation(1, 3, 0.9, RelationType::BoughtTogether).unwrap();
+    let engine = engine.with_default_category(Category::Electronics);
+
+    let ids: Vec<u64> = engine
+        .hybrid_search(None, &SearchFilters::new(), true)
+        .iter()
+        .map(|r| r.product.id)
+        .collect();
+    assert_eq!(ids, vec![1]);
+
+    let clothing = SearchFilters::new().category(Category::Clothing);
+    assert_eq!(engine.hybrid_search(None, &clothing, true).len(), 2);
+}
+
+#[test]
+fn test_default_category_applies_to_recommendations() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Smart Watch", "Apple", Category::Electronics, 400.0, 4.5));
+    engine.add_product(create_test_product(2, "Leather Strap", "Fossil", Category::Clothing, 50.0, 4.2));
+    engine.add_product(create_test_product(3, "Charger", "Apple", Category::Electronics, 30.0, 4.0));
+    engine.add_product(create_test_product(4, "Watch Box", "Fossil", Category::Clothing, 20.0, 3.9));
+    engine.add_product(create_test_product(5, "Smart Ring", "Oura", Category::Electronics, 300.0, 4.1));
+    for (id, relation_type) in [
+        (2, RelationType::BoughtTogether),
+        (3, RelationType::BoughtTogether),
+        (4, RelationType::AlsoViewed),
+        (5, RelationType::AlsoViewed),
+    ] {
+        engine.add_product_relation(1, id, 0.9, relation_type).unwrap();
+    }
+    let engine = engine.with_default_category(Category::Electronics);
+
+    let ids = |results: Vec<SearchResult>| -> Vec<u64> {
+        let mut ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(engine.get_recommendations_for_product(1, 10)), vec![3, 5]);
+    assert_eq!(ids(engine.get_frequently_bought_together(1)), vec![3]);
+    assert_eq!(ids(engine.get_also_viewed(1)), vec![5]);
+    let apple = SearchFilters::new().brand("Apple".to_string());
+    assert_eq!(ids(engine.recommend_from_filters(&apple, 10)), vec![5]);
+    assert!(engine.search_with_recommendations("watch", true, 10).iter().all(|r| r.product.category == Category::Electronics));
+
+    // Asking for a category explicitly still works.
+    assert_eq!(ids(engine.trending_in_category(&Category::Clothing, 10)), vec![2, 4]);
+}
+
+#[test]
+fn test_search_interleaved() {
+    let mut engine = SearchEngine::new();
+    // Relevance favors exact "Laptop" name matches; popularity favors rating.
+    for (id, brand, rating) in [(1, "Dell", 3.0), (2, "HP", 2.5)] {
+        let mut product = create_test_product(id, "Laptop", brand, Category::Electronics, 1000.0, rating);
+        product.add_tag("laptop".to_string());
+        engine.add_product(product);
+    }
+    engine.add_product(create_test_product(3, "Gaming Laptop Stand", "Acme", Category::Electronics, 50.0, 5.0));
+    engine.add_product(create_test_product(4, "Laptop Sleeve Case", "Acme", Category::Electronics, 20.0, 4.8));
+    engine.add_product(create_test_product(5, "Laptop Cooling Pad", "Acme", Category::Electronics, 30.0, 1.0));
+
+    let relevance: Vec<u64> = engine.basic_search("laptop").iter().map(|r| r.product.id).collect();
+    assert_eq!(&relevance[..2], &[1, 2]);
+
+    let results = engine.search_interleaved("laptop", 10);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+
+    // relevance, popularity, relevance, popularity, ...
+    assert_eq!(&ids[..4], &[1, 3, 2, 4]);
+    assert_eq!(results[1].score, 5.0);
+
+    let unique: HashSet<u64> = ids.iter().copied().collect();
+    assert_eq!(unique.len(), ids.len());
+    assert_eq!(ids.len(), 5);
+    assert_eq!(engine.search_interleaved("laptop", 3).len(), 3);
+}
+
+#[test]
+fn test_region_restrictions() {
+    let mut engine = SearchEngine::new();
+    let mut restricted = create_test_product(1, "Smart Speaker", "Acme", Category::Electronics, 80.0, 4.0);
+    restricted.restricted_regions.push("EU".to_string());
+    engine.add_product(restricted);
+    engine.add_product(create_test_product(2, "Bluetooth Speaker", "Acme", Category::Electronics, 40.0, 4.2));
+
+    let eu = SearchFilters::new().region("eu".to_string());
+    let ids: Vec<u64> = engine.search_with_filters(Some("speaker"), &eu).iter().map(|r| r.product.id).collect();
+    assert_eq!(ids, vec![2]);
+
+    let us = SearchFilters::new().region("US".to_string());
+    assert_eq!(engine.search_with_filters(Some("speaker"), &us).len(), 2);
+    assert_eq!(engine.search_with_filters(None, &eu).len(), 1);
+    assert_eq!(SearchFilters::from_query_string(&eu.to_query_string()).unwrap(), eu);
+    assert!(engine.index().verify_consistency().is_ok());
+}
+
+#[test]
+fn test_list_diversity() {
+    let mut engine = SearchEngine::new();
+    let tagged = |id, name: &str, category, tags: &[&str]| {
+        let mut product = create_test_product(id, name, "Acme", category, 10.0, 4.0);
+        for tag in tags {
+            product.add_tag(tag.to_string());
+        }
+        product
+    };
+
+    engine.add_product(tagged(1, "Phone Case", Category::Electronics, &["phone", "case"]));
+    engine.add_product(tagged(2, "Phone Cover", Category::Electronics, &["phone", "case"]));
+    engine.add_product(tagged(3, "Phone Shell", Category::Electronics, &["Phone", "case"]));
+    engine.add_product(tagged(4, "Yoga Mat", Category::Sports, &["yoga"]));
+    engine.add_product(tagged(5, "Novel", Category::Books, &["fiction"]));
+
+    let results_for = |ids: &[u64]| -> Vec<SearchResult> {
+        ids.iter()
+            .map(|&id| SearchResult {
+                product: engine.get_product(id).unwrap().clone(),
+                score: 1.0,
+                match_type: MatchType::Recommendation,
+                score_components: None,
+            })
+            .collect()
+    };
+
+    let homogeneous = engine.list_diversity(&results_for(&[1, 2, 3]));
+    let mixed = engine.list_diversity(&results_for(&[1, 4, 5]));
+
+    assert!(homogeneous < 0.1);
+    assert!(mixed > 0.9);
+    assert_eq!(engine.list_diversity(&results_for(&[1])), 0.0);
+}
+
+#[test]
+fn test_add_and_remove_tag_on_product() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 30.0, 4.0));
+
+    let tagged = |engine: &SearchEngine| {
+        let filters = SearchFilters::new().add_tag("clearance".to_string());
+        engine.search_with_filters(None, &filters).iter().map(|r| r.product.id).collect::<Vec<u64>>()
+    };
+
+    assert!(engine.add_tag_to_product(1, "Clearance".to_string()));
+    assert!(!engine.add_tag_to_product(1, "clearance".to_string()));
+    assert!(!engine.add_tag_to_product(99, "clearance".to_string()));
+    assert_eq!(tagged(&engine), vec![1]);
+    assert_eq!(engine.index().search_by_tag("clearance"), vec![1]);
+
+    assert!(engine.remove_tag_from_product(1, "CLEARANCE"));
+    assert!(!engine.remove_tag_from_product(1, "clearance"));
+    assert!(tagged(&engine).is_empty());
+    assert!(engine.get_product(1).unwrap().tags.is_empty());
+    assert!(!engine.index().all_tags().contains(&"clearance".to_string()));
+    assert!(engine.index().verify_consistency().is_ok());
+}
+
+#[test]
+fn test_search_sample_is_reproducible() {
+    let mut engine = SearchEngine::new();
+    for id in 1..=30 {
+        engine.add_product(create_test_product(id, &format!("Widget {}", id), "Acme", Category::Electronics, 10.0, (id % 5) as f32));
+    }
+
+    let sample_ids = |seed| -> Vec<u64> { engine.search_sample("widget", 5, seed).iter().map(|r| r.product.id).collect() };
+
+    let first = sample_ids(42);
+    assert_eq!(first.len(), 5);
+    assert_eq!(first, sample_ids(42));
+
+    let unique: HashSet<u64> = first.iter().copied().collect();
+    assert_eq!(unique.len(), 5);
+
+    let differing = (0..10u64).filter(|&seed| sample_ids(seed) != first).count();
+    assert!(differing >= 8);
+    assert_eq!(engine.search_sample("widget", 100, 7).len(), 30);
+}
+
+#[test]
+fn test_trending_in_category() {
+    let mut engine = SearchEngine::new();
+    engine.add_product(create_test_product(1, "Laptop", "Dell", Category::Electronics, 1000.0, 4.5));
+    engine.add_product(create_test_product(2, "Mouse", "Logitech", Category::Electronics, 30.0, 4.0));
+    engine.add_product(create_test_product(3, "Keyboard", "Logitech", Category::Electronics, 60.0, 4.1));
+    engine.add_product(create_test_product(4, "Laptop Bag", "Targus", Category::Clothing, 50.0, 4.3));
+    engine.add_product(create_test_product(5, "Hoodie", "Nike", Category::Clothing, 70.0, 4.2));
+
+    // The mouse is the hub, linked from both categories
+    engine.add_product_relation(2, 1, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(2, 3, 0.9, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(2, 4, 0.7, RelationType::BoughtTogether).unwrap();
+    engine.add_product_relation(2, 5, 0.7, RelationType::AlsoViewed).unwrap();
+    engine.add_product_relation(4, 5, 0.2, RelationType::Similar).unwrap();
+
+    let results = engine.trending_in_category(&Category::Electronics, 10);
+    let ids: Vec<u64> = results.iter().map(|r| r.product.id).collect();
+
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ids[0], 2);
+    assert!(results.iter().all(|r| r.product.category == Category::Electronics));
+    assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+    let clothing = engine.trending_in_category(&Category::Clothing, 1);
+    assert_eq!(clothing.len(), 1);
+    assert_eq!(clothing[0].product.category, Category::Clothing);
+}